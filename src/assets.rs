@@ -0,0 +1,65 @@
+use macroquad::prelude::*;
+
+/// Handle to a texture in [`Assets`].
+///
+/// Handles are plain numbers, so game state can hold on to them without touching the GPU and the
+/// simulation keeps running headless.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TextureId(usize);
+
+pub const PLAYER: TextureId = TextureId(0);
+pub const ENEMY: TextureId = TextureId(1);
+pub const BULLET: TextureId = TextureId(2);
+pub const SPAWN_POINT: TextureId = TextureId(3);
+
+/// Registered in this order, so they line up with the constants above
+const BUILT_IN: [&str; 4] = [
+    "assets/player.png",
+    "assets/enemy.png",
+    "assets/bullet.png",
+    "assets/spawn_point.png",
+];
+
+/// Every texture the game draws, each loaded from disk exactly once.
+///
+/// Registering a path only hands out a handle; the files are read by [`Assets::load`], which
+/// the game calls once at startup.
+pub struct Assets {
+    paths: Vec<String>,
+    textures: Vec<Texture2D>,
+}
+
+impl Assets {
+    pub fn new() -> Assets {
+        let mut assets = Assets {
+            paths: Vec::new(),
+            textures: Vec::new(),
+        };
+        for path in BUILT_IN {
+            assets.register(path);
+        }
+        assets
+    }
+
+    /// Returns the handle for `path`, registering it the first time it is seen.
+    pub fn register(&mut self, path: &str) -> TextureId {
+        match self.paths.iter().position(|known| known == path) {
+            Some(index) => TextureId(index),
+            None => {
+                self.paths.push(path.to_owned());
+                TextureId(self.paths.len() - 1)
+            }
+        }
+    }
+
+    /// Loads every registered texture that isn't loaded yet.
+    pub async fn load(&mut self) {
+        for path in self.paths[self.textures.len()..].iter() {
+            self.textures.push(load_texture(path).await.unwrap());
+        }
+    }
+
+    pub fn texture(&self, id: TextureId) -> &Texture2D {
+        &self.textures[id.0]
+    }
+}
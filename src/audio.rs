@@ -0,0 +1,146 @@
+use std::fs;
+
+use macroquad::prelude::*;
+use quad_snd::{
+    decoder::read_wav_ext,
+    mixer::{PlaybackStyle, Sound, SoundMixer, Volume},
+};
+
+use crate::settings::Settings;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SoundType {
+    MenuClick,
+    EnemyHit,
+    PlayerDie,
+    Explosion,
+}
+
+/// Handle to a sound in [`Audio`], either one of the built in [`SoundType`]s or one loaded from
+/// a path named in a data file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ClipId(usize);
+
+impl From<SoundType> for ClipId {
+    fn from(sound: SoundType) -> ClipId {
+        ClipId(sound as usize)
+    }
+}
+
+/// A decoded sound plus the rules for how often it may play.
+struct Clip {
+    /// Where the sound was loaded from, empty for the built in ones
+    path: String,
+    sound: Sound,
+    /// Seconds
+    length: f64,
+    /// How loud the sound is relative to the others, before the player's volume settings
+    volume: f32,
+    /// How many copies may be playing at once
+    max_voices: usize,
+    /// Seconds that must pass before the sound can start again
+    min_interval: f64,
+    last_played: f64,
+    /// When each copy that is still playing will end
+    voices: Vec<f64>,
+}
+
+impl Clip {
+    fn new(bytes: &[u8], volume: f32, max_voices: usize, min_interval: f64) -> Clip {
+        let sound = read_wav_ext(bytes, PlaybackStyle::Once).unwrap();
+        let frames = sound.samples.len() as f64 / sound.channels.max(1) as f64;
+        Clip {
+            path: String::new(),
+            length: frames / sound.sample_rate as f64,
+            sound,
+            volume,
+            max_voices,
+            min_interval,
+            last_played: f64::NEG_INFINITY,
+            voices: Vec::new(),
+        }
+    }
+}
+
+/// Every sound effect, decoded once at startup, and the mixer they play through.
+///
+/// Each sound has a voice cap and a minimum gap between starts, so a shotgun volley into a
+/// crowd produces one hit sound instead of a wall of noise.
+pub struct Audio {
+    mixer: SoundMixer,
+    clips: Vec<Clip>,
+    /// Master times sound effect volume from the settings
+    sfx_volume: f32,
+}
+
+impl Audio {
+    pub fn new() -> Audio {
+        // Indexed by SoundType
+        let clips = vec![
+            Clip::new(
+                include_bytes!("../assets/sounds/button_click.wav"),
+                0.5,
+                2,
+                0.05,
+            ),
+            Clip::new(
+                include_bytes!("../assets/sounds/enemy_hit.wav"),
+                0.2,
+                3,
+                0.08,
+            ),
+            Clip::new(
+                include_bytes!("../assets/sounds/player_die.wav"),
+                0.3,
+                1,
+                0.5,
+            ),
+            Clip::new(
+                include_bytes!("../assets/sounds/explosion.wav"),
+                0.4,
+                3,
+                0.06,
+            ),
+        ];
+
+        Audio {
+            mixer: SoundMixer::new(),
+            clips,
+            sfx_volume: 1.0,
+        }
+    }
+
+    pub fn apply_settings(&mut self, settings: &Settings) {
+        self.sfx_volume = settings.master_volume * settings.sfx_volume;
+    }
+
+    /// Decodes the wav at `path`, or hands back the clip already loaded from it.
+    ///
+    /// Sounds from data files all get the same volume and limits as gunshots.
+    pub fn load(&mut self, path: &str) -> ClipId {
+        if let Some(index) = self.clips.iter().position(|clip| clip.path == path) {
+            return ClipId(index);
+        }
+
+        let bytes = fs::read(path).unwrap_or_else(|err| panic!("{}: {}", path, err));
+        let mut clip = Clip::new(&bytes, 0.3, 6, 0.03);
+        clip.path = path.to_owned();
+        self.clips.push(clip);
+        ClipId(self.clips.len() - 1)
+    }
+
+    pub fn play(&mut self, sound: impl Into<ClipId>) {
+        let now = get_time();
+        let clip = &mut self.clips[sound.into().0];
+
+        clip.voices.retain(|&end| end > now);
+        if now - clip.last_played < clip.min_interval || clip.voices.len() >= clip.max_voices {
+            return;
+        }
+
+        clip.last_played = now;
+        clip.voices.push(now + clip.length);
+        self.mixer
+            .play_ext(clip.sound.clone(), Volume(clip.volume * self.sfx_volume));
+    }
+}
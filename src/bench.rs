@@ -0,0 +1,100 @@
+use std::{
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use ::rand::Rng;
+use macroquad::prelude::*;
+
+use crate::assets::Assets;
+use crate::bullet::{Bullet, Faction};
+use crate::content::Content;
+use crate::enemy::{Enemy, EnemyKind};
+use crate::input::Input;
+use crate::settings::Difficulty;
+use crate::world::{World, TIME_STEP};
+
+const ENEMIES: usize = 1000;
+const BULLETS: usize = 300;
+/// Ten seconds of play, long enough for the horde to close in and pile up on the player
+const TICKS: usize = 600;
+
+/// Runs the simulation headless with a full horde and a screen full of bullets and reports how
+/// long ticks take compared to the 60 ticks a second the game needs.
+pub fn run() {
+    let mut world = World::new(
+        0,
+        Difficulty::Normal,
+        Rc::new(Content::load(&mut Assets::new())),
+    );
+    let size = world.size;
+    // Nothing may die, otherwise the load drops off as the run goes on
+    world.player.health = i32::MAX;
+    for _ in 0..ENEMIES {
+        let position = random_point(&mut world, size);
+        let mut enemy = Enemy::new(position, EnemyKind(0), &world.content.enemies[0]);
+        enemy.health = i32::MAX;
+        enemy.id = world.next_enemy_id;
+        world.next_enemy_id += 1;
+        world.enemies.push(enemy);
+    }
+
+    let input = Input {
+        aim: size / 2.0,
+        ..Default::default()
+    };
+
+    let mut total = Duration::ZERO;
+    let mut worst = Duration::ZERO;
+    for _ in 0..TICKS {
+        // Bullets leave the arena all the time, keep the count topped up
+        while world.bullets.len() < BULLETS {
+            let position = random_point(&mut world, size);
+            let target = random_point(&mut world, size);
+            world.bullets.push(Bullet::new(
+                position,
+                target,
+                true,
+                420.0,
+                5,
+                f32::MAX,
+                Faction::Player,
+            ));
+        }
+
+        let start = Instant::now();
+        world.tick(&input, TIME_STEP);
+        let elapsed = start.elapsed();
+        total += elapsed;
+        worst = worst.max(elapsed);
+    }
+
+    let average = total / TICKS as u32;
+    let budget = Duration::from_secs_f32(TIME_STEP);
+    println!(
+        "{} enemies, {} bullets, {} ticks",
+        world.enemies.len(),
+        BULLETS,
+        TICKS
+    );
+    println!("average tick {:?}, worst tick {:?}", average, worst);
+    println!(
+        "{:.0} ticks per second, {:.1}% of the {:?} budget",
+        1.0 / average.as_secs_f64(),
+        average.as_secs_f64() / budget.as_secs_f64() * 100.0,
+        budget
+    );
+}
+
+/// Somewhere in the level that isn't inside a wall.
+fn random_point(world: &mut World, size: Vec2) -> Vec2 {
+    loop {
+        let point = Vec2::new(
+            world.rng.gen_range(0.0..size.x),
+            world.rng.gen_range(0.0..size.y),
+        );
+        if !world.content.level.is_solid_at(point) {
+            return point;
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use serde::Deserialize;
+
+use crate::enemy::{Behaviour, EnemyKind, RangedAttack};
+use crate::pickup::Drop;
+
+/// One entry of `assets/bosses.toml`.
+///
+/// A boss fights as an enemy like any other, using an archetype built from its stats that the
+/// spawner never picks on its own, with its movement and attack swapped out as it loses health.
+#[derive(Debug, Deserialize)]
+pub struct BossDef {
+    pub name: String,
+    pub health: i32,
+    /// Width and height of the collision box and sprite
+    pub size: [f32; 2],
+    pub sprite: String,
+    /// RGBA
+    pub tint: [u8; 4],
+    /// Health taken from the player on touch
+    pub contact_damage: i32,
+    /// Seconds between touches doing damage
+    pub attack_cooldown: f64,
+    /// Added to the score on death
+    pub score: i32,
+    /// Arrives with this wave
+    pub at_wave: Option<u32>,
+    /// Arrives once the score reaches this
+    pub at_score: Option<i32>,
+    pub intro_sound: String,
+    pub defeat_sound: String,
+    /// Rolled in order when it dies, the first to come up being dropped
+    #[serde(default)]
+    pub drops: Vec<Drop>,
+    /// In order, the first one starting at full health
+    #[serde(rename = "phase")]
+    pub phases: Vec<BossPhase>,
+    /// The archetype the boss fights as, added to the enemy table when the content is loaded
+    #[serde(skip)]
+    pub kind: Option<EnemyKind>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BossPhase {
+    /// Starts once the boss is down to this fraction of its health
+    pub below: f32,
+    /// Pixels per second
+    pub speed: f32,
+    pub behaviour: Behaviour,
+    pub ranged: Option<RangedAttack>,
+}
+
+/// Handle to a boss, its position in the boss table.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BossId(pub usize);
+
+/// The boss fight going on, or the last one to have finished.
+#[derive(Clone, Copy, Debug)]
+pub struct Encounter {
+    pub boss: BossId,
+    pub arrived_at: f64,
+    pub defeated_at: Option<f64>,
+}
@@ -1,26 +1,99 @@
 use macroquad::prelude::*;
+use serde::Deserialize;
+
+use crate::assets::{self, TextureId};
+
+/// Which side fired a bullet, and so which side it can hurt.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Faction {
+    Player,
+    Enemy,
+}
+
+/// How a bullet behaves beyond flying straight and stopping at the first thing it hits.
+///
+/// Weapons set these in `assets/weapons.toml` and power-ups add to them, and any mix of them
+/// works together.
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default)]
+pub struct Projectile {
+    /// Enemies it passes through before stopping
+    pub piercing: u32,
+    /// Times it glances off a wall rather than stopping there
+    pub bounces: u32,
+    /// Degrees a second it turns towards the nearest enemy
+    pub homing: f32,
+    /// Pixels around where it stops that take blast damage, 0 for no blast
+    pub blast_radius: f32,
+    /// Fraction of the bullet's damage dealt to everything in the blast
+    pub blast_damage: f32,
+    /// Seconds it flies before fizzling out, on top of its range running out
+    pub lifetime: Option<f32>,
+}
+
+impl Default for Projectile {
+    fn default() -> Projectile {
+        Projectile {
+            piercing: 0,
+            bounces: 0,
+            homing: 0.0,
+            blast_radius: 0.0,
+            blast_damage: 0.5,
+            lifetime: None,
+        }
+    }
+}
+
+/// Where a bullet went off, kept around a moment after to be drawn.
+pub struct Blast {
+    /// Middle of the blast
+    pub position: Vec2,
+    pub radius: f32,
+    pub at: f64,
+}
 
 pub struct Bullet {
     pub position: Vec2,
-    pub texture: Texture2D,
+    pub prev_position: Vec2,
+    pub texture: TextureId,
     pub coll_rect: Rect,
-    pub target: Vec2,
     pub is_active: bool,
     pub velocity: Vec2,
+    /// Pixels per second
     pub speed: f32,
+    pub damage: i32,
+    /// Pixels it can still travel before fizzling out
+    pub range: f32,
+    pub owner: Faction,
+    /// What's left of its behaviours, with `piercing` and `bounces` counting down as they're used
+    pub projectile: Projectile,
+    /// Seconds it can still fly before fizzling out
+    pub lifetime: f32,
+    /// Ids of the enemies it has passed through, so it hurts each one only once
+    pub hit: Vec<u32>,
 }
 impl Bullet {
-    pub async fn new(position: Vec2, target: Vec2, is_active: bool, speed: f32) -> Bullet {
-        let bullet_texture = load_texture("assets/bullet.png").await.unwrap();
+    pub fn new(position: Vec2, target: Vec2, is_active: bool, speed: f32, damage: i32, range: f32, owner: Faction) -> Bullet {
         let direction = target - position;
         Bullet {
-            position: position,
-            texture: bullet_texture,
+            position,
+            prev_position: position,
+            texture: assets::BULLET,
             coll_rect: Rect::new(position.x, position.y, 8.0, 8.0),
-            target: target,
-            is_active: is_active,
-            velocity: direction.normalize(),
-            speed: speed,
+            is_active,
+            velocity: direction.normalize_or_zero(),
+            speed,
+            damage,
+            range,
+            owner,
+            projectile: Projectile::default(),
+            lifetime: f32::INFINITY,
+            hit: Vec::new(),
         }
     }
+
+    /// What everything caught in its blast takes.
+    pub fn blast_damage(&self) -> i32 {
+        (self.damage as f32 * self.projectile.blast_damage).round() as i32
+    }
 }
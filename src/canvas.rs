@@ -0,0 +1,124 @@
+use macroquad::prelude::*;
+
+/// The size everything in the game is drawn at, whatever the size of the window.
+pub const CANVAS_SIZE: Vec2 = Vec2::new(800.0, 600.0);
+
+/// How quickly the camera catches up with the player. The gap shrinks by about two thirds
+/// every `1 / CAMERA_FOLLOW_RATE` seconds.
+const CAMERA_FOLLOW_RATE: f32 = 8.0;
+
+/// An offscreen image the game is drawn into at [`CANVAS_SIZE`], which is then scaled onto the
+/// window as large as it fits, with black bars filling whatever is left over.
+pub struct Canvas {
+    target: RenderTarget,
+}
+
+impl Canvas {
+    pub fn new() -> Canvas {
+        let target = render_target(CANVAS_SIZE.x as u32, CANVAS_SIZE.y as u32);
+        target.texture.set_filter(FilterMode::Linear);
+        Canvas { target }
+    }
+
+    /// Sends drawing to the canvas, with everything moved by `offset` canvas pixels.
+    pub fn begin(&self, offset: Vec2) {
+        let mut camera = Camera2D::from_display_rect(Rect::new(
+            offset.x,
+            offset.y,
+            CANVAS_SIZE.x,
+            CANVAS_SIZE.y,
+        ));
+        camera.render_target = Some(self.target.clone());
+        set_camera(&camera);
+    }
+
+    /// Draws the canvas onto the window.
+    pub fn present(&self) {
+        set_default_camera();
+        clear_background(BLACK);
+
+        let view = viewport();
+        draw_texture_ex(
+            &self.target.texture,
+            view.x,
+            view.y,
+            WHITE,
+            DrawTextureParams {
+                dest_size: Some(view.size()),
+                // Render targets come out upside down
+                flip_y: true,
+                ..Default::default()
+            },
+        );
+    }
+}
+
+/// The part of the window the canvas is drawn to.
+pub fn viewport() -> Rect {
+    let screen = vec2(screen_width(), screen_height());
+    let scale = (screen.x / CANVAS_SIZE.x).min(screen.y / CANVAS_SIZE.y);
+    let size = CANVAS_SIZE * scale;
+    let origin = (screen - size) / 2.0;
+    Rect::new(origin.x, origin.y, size.x, size.y)
+}
+
+/// Converts a point in window pixels, like the mouse position, to canvas pixels.
+pub fn screen_to_canvas(point: Vec2) -> Vec2 {
+    let view = viewport();
+    (point - view.point()) * CANVAS_SIZE / view.size()
+}
+
+/// Top left corner of the canvas in the window, where the menus go.
+pub fn menu_origin() -> Vec2 {
+    viewport().point().floor()
+}
+
+/// Window pixels per canvas pixel. The UI is always drawn in window pixels, so the menus are
+/// laid out for the canvas size and scaled up or down by this to cover it.
+pub fn ui_scale() -> f32 {
+    viewport().w / CANVAS_SIZE.x
+}
+
+/// Keeps the view on the player, easing after them rather than sticking to them rigidly.
+pub struct FollowCamera {
+    /// Top left of the view in world pixels
+    pub position: Vec2,
+}
+
+impl FollowCamera {
+    pub fn new() -> FollowCamera {
+        FollowCamera {
+            position: Vec2::ZERO,
+        }
+    }
+
+    /// Moves part of the way towards centring `target`, more the longer `dt` is.
+    pub fn follow(&mut self, target: Vec2, arena: Vec2, dt: f32) {
+        let goal = view_around(target, arena);
+        self.position += (goal - self.position) * (1.0 - (-CAMERA_FOLLOW_RATE * dt).exp());
+    }
+
+    /// Jumps straight to centring `target`.
+    pub fn snap(&mut self, target: Vec2, arena: Vec2) {
+        self.position = view_around(target, arena);
+    }
+}
+
+/// The view centred on `target` but kept inside the arena, or centred on the arena if it is
+/// smaller than the canvas.
+fn view_around(target: Vec2, arena: Vec2) -> Vec2 {
+    let centred = target - CANVAS_SIZE / 2.0;
+    let max = arena - CANVAS_SIZE;
+    vec2(
+        if max.x > 0.0 {
+            centred.x.clamp(0.0, max.x)
+        } else {
+            max.x / 2.0
+        },
+        if max.y > 0.0 {
+            centred.y.clamp(0.0, max.y)
+        } else {
+            max.y / 2.0
+        },
+    )
+}
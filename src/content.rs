@@ -0,0 +1,250 @@
+use std::fs;
+
+use serde::{de::DeserializeOwned, Deserialize};
+
+use crate::assets::Assets;
+use crate::boss::BossDef;
+use crate::enemy::{EnemyDef, EnemyKind};
+use crate::level::Level;
+use crate::pickup::{Drop, Effect, PickupDef, PickupKind};
+use crate::power_up::{PowerUpDef, PowerUpId};
+use crate::wave::WaveTable;
+use crate::weapon::{WeaponDef, WeaponId};
+
+/// The game's data files, loaded once at startup and shared by every [`World`](crate::world::World).
+pub struct Content {
+    pub weapons: Vec<WeaponDef>,
+    pub enemies: Vec<EnemyDef>,
+    pub waves: WaveTable,
+    pub bosses: Vec<BossDef>,
+    pub power_ups: Vec<PowerUpDef>,
+    pub pickups: Vec<PickupDef>,
+    pub level: Level,
+}
+
+#[derive(Deserialize)]
+struct WeaponFile {
+    weapon: Vec<WeaponDef>,
+}
+
+#[derive(Deserialize)]
+struct PowerUpFile {
+    #[serde(default)]
+    power_up: Vec<PowerUpDef>,
+}
+
+#[derive(Deserialize)]
+struct PickupFile {
+    #[serde(default)]
+    pickup: Vec<PickupDef>,
+}
+
+#[derive(Deserialize)]
+struct EnemyFile {
+    enemy: Vec<EnemyDef>,
+}
+
+#[derive(Deserialize)]
+struct BossFile {
+    #[serde(default)]
+    boss: Vec<BossDef>,
+}
+
+impl Content {
+    /// Reads everything from the `assets` folder, registering any textures the data names with
+    /// `assets` so they get loaded alongside the built in ones.
+    ///
+    /// Broken data files are a mistake to fix rather than something to play around, so this
+    /// panics with the file and the parse error.
+    pub fn load(assets: &mut Assets) -> Content {
+        let weapons: WeaponFile = load_toml("assets/weapons.toml");
+        if weapons.weapon.is_empty() {
+            panic!("assets/weapons.toml: at least one weapon is needed");
+        }
+        if weapons.weapon[0].unlock.is_some() {
+            panic!("assets/weapons.toml: the first weapon needs to be there from the start");
+        }
+        if weapons.weapon[0].reserve.is_some() {
+            panic!(
+                "assets/weapons.toml: the first weapon needs an endless reserve to fall back on"
+            );
+        }
+        for weapon in weapons.weapon.iter() {
+            if weapon.magazine == Some(0) {
+                panic!(
+                    "assets/weapons.toml: {} needs room for at least one shot in its magazine",
+                    weapon.name
+                );
+            }
+        }
+
+        let power_ups: PowerUpFile = load_toml("assets/power_ups.toml");
+        for power_up in power_ups.power_up.iter() {
+            if power_up.duration <= 0.0 {
+                panic!(
+                    "assets/power_ups.toml: {} would run out the moment it started",
+                    power_up.name
+                );
+            }
+            if power_up.max_stacks == 0 {
+                panic!(
+                    "assets/power_ups.toml: {} needs a max_stacks of at least 1",
+                    power_up.name
+                );
+            }
+            if !(0.0..=1.0).contains(&power_up.modifiers.shield) {
+                panic!(
+                    "assets/power_ups.toml: {} needs a shield from 0 to 1",
+                    power_up.name
+                );
+            }
+        }
+
+        let mut pickups: PickupFile = load_toml("assets/pickups.toml");
+        for pickup in pickups.pickup.iter_mut() {
+            pickup.texture = assets.register(&pickup.sprite);
+            match &mut pickup.effect {
+                Effect::Weapon { weapon, id, .. } => {
+                    let index = weapons.weapon.iter().position(|def| &def.name == weapon);
+                    match index {
+                        Some(index) => *id = Some(WeaponId(index)),
+                        None => panic!("assets/pickups.toml: there is no weapon called {}", weapon),
+                    }
+                }
+                Effect::PowerUp { power_up, id } => {
+                    let index = power_ups
+                        .power_up
+                        .iter()
+                        .position(|def| &def.name == power_up);
+                    match index {
+                        Some(index) => *id = Some(PowerUpId(index)),
+                        None => panic!(
+                            "assets/pickups.toml: there is no power-up called {}",
+                            power_up
+                        ),
+                    }
+                }
+                Effect::Health { .. } => {}
+            }
+        }
+
+        let mut enemies: EnemyFile = load_toml("assets/enemies.toml");
+        if enemies.enemy.iter().all(|enemy| enemy.spawn_weight == 0) {
+            panic!("assets/enemies.toml: at least one enemy with a spawn_weight is needed");
+        }
+        for enemy in enemies.enemy.iter_mut() {
+            if enemy.health <= 0 {
+                panic!(
+                    "assets/enemies.toml: {} would die the moment it spawned",
+                    enemy.name
+                );
+            }
+            enemy.texture = assets.register(&enemy.sprite);
+            resolve_drops(&mut enemy.drops, &pickups.pickup, "assets/enemies.toml");
+        }
+
+        let mut waves: WaveTable = load_toml("assets/waves.toml");
+        if waves.waves.is_empty() {
+            panic!("assets/waves.toml: at least one wave is needed");
+        }
+        for group in waves
+            .waves
+            .iter_mut()
+            .flat_map(|wave| wave.groups.iter_mut())
+        {
+            if let Some(name) = &group.enemy {
+                let index = enemies.enemy.iter().position(|enemy| &enemy.name == name);
+                match index {
+                    Some(index) => group.kind = Some(EnemyKind(index)),
+                    None => panic!("assets/waves.toml: there is no enemy called {}", name),
+                }
+            }
+        }
+
+        // After the waves, so they can't name a boss and send it without its phases
+        let mut bosses: BossFile = load_toml("assets/bosses.toml");
+        for boss in bosses.boss.iter_mut() {
+            if boss.health <= 0 {
+                panic!(
+                    "assets/bosses.toml: {} would die the moment it spawned",
+                    boss.name
+                );
+            }
+            if boss.at_wave.is_none() && boss.at_score.is_none() {
+                panic!(
+                    "assets/bosses.toml: {} needs an at_wave or an at_score to ever appear",
+                    boss.name
+                );
+            }
+            if boss.phases.first().is_none_or(|phase| phase.below < 1.0) {
+                panic!(
+                    "assets/bosses.toml: {} needs a first phase starting at full health",
+                    boss.name
+                );
+            }
+            if boss
+                .phases
+                .windows(2)
+                .any(|pair| pair[1].below >= pair[0].below)
+            {
+                panic!("assets/bosses.toml: {} has phases out of order", boss.name);
+            }
+            resolve_drops(&mut boss.drops, &pickups.pickup, "assets/bosses.toml");
+
+            boss.kind = Some(EnemyKind(enemies.enemy.len()));
+            enemies.enemy.push(EnemyDef {
+                name: boss.name.clone(),
+                health: boss.health,
+                speed: boss.phases[0].speed,
+                size: boss.size,
+                sprite: boss.sprite.clone(),
+                texture: assets.register(&boss.sprite),
+                tint: boss.tint,
+                contact_damage: boss.contact_damage,
+                attack_cooldown: boss.attack_cooldown,
+                score: boss.score,
+                behaviour: boss.phases[0].behaviour,
+                ranged: None,
+                drops: boss.drops.clone(),
+                spawn_weight: 0,
+            });
+        }
+
+        let mut level = Level::load("assets/arena.json", assets);
+        for spot in level.pickups.iter_mut() {
+            spot.kind = Some(pickup_kind(
+                &pickups.pickup,
+                &spot.name,
+                "assets/arena.json",
+            ));
+        }
+
+        Content {
+            weapons: weapons.weapon,
+            enemies: enemies.enemy,
+            waves,
+            bosses: bosses.boss,
+            power_ups: power_ups.power_up,
+            pickups: pickups.pickup,
+            level,
+        }
+    }
+}
+
+fn resolve_drops(drops: &mut [Drop], pickups: &[PickupDef], path: &str) {
+    for drop in drops.iter_mut() {
+        drop.kind = Some(pickup_kind(pickups, &drop.pickup, path));
+    }
+}
+
+fn pickup_kind(pickups: &[PickupDef], name: &str, path: &str) -> PickupKind {
+    match pickups.iter().position(|pickup| pickup.name == name) {
+        Some(index) => PickupKind(index),
+        None => panic!("{}: there is no pickup called {}", path, name),
+    }
+}
+
+fn load_toml<T: DeserializeOwned>(path: &str) -> T {
+    let text = fs::read_to_string(path).unwrap_or_else(|err| panic!("{}: {}", path, err));
+    toml::from_str(&text).unwrap_or_else(|err| panic!("{}: {}", path, err))
+}
@@ -1,26 +1,142 @@
 use macroquad::prelude::*;
+use serde::Deserialize;
+
+use crate::assets::{self, TextureId};
+use crate::boss::BossId;
+use crate::pickup::Drop;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Behaviour {
+    /// Walks straight at the player
+    Chase,
+    /// Circles the player while slowly closing in
+    Orbit,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FirePattern {
+    /// One projectile straight at the player
+    Aimed,
+    /// Fanned out around the line to the player
+    Spread,
+    /// Evenly all the way round
+    Radial,
+}
+
+/// How an enemy shoots, for the ones that do.
+#[derive(Debug, Deserialize)]
+pub struct RangedAttack {
+    pub pattern: FirePattern,
+    /// Seconds between volleys
+    pub fire_rate: f64,
+    /// Per projectile
+    pub damage: i32,
+    /// Pixels per second
+    pub projectile_speed: f32,
+    /// Pixels a projectile flies, and how close the player has to be before the enemy opens fire
+    pub range: f32,
+    /// Projectiles per volley, for spreads and radial bursts
+    #[serde(default = "one")]
+    pub count: u32,
+    /// Degrees between the outermost projectiles of a spread
+    #[serde(default)]
+    pub spread: f32,
+}
+
+/// One entry of `assets/enemies.toml`.
+#[derive(Debug, Deserialize)]
+pub struct EnemyDef {
+    pub name: String,
+    pub health: i32,
+    /// Pixels per second
+    pub speed: f32,
+    /// Width and height of the collision box and sprite
+    pub size: [f32; 2],
+    pub sprite: String,
+    /// Registered from `sprite` when the content is loaded
+    #[serde(skip, default = "default_texture")]
+    pub texture: TextureId,
+    /// RGBA
+    pub tint: [u8; 4],
+    /// Health taken from the player on touch
+    pub contact_damage: i32,
+    /// Seconds between touches doing damage
+    pub attack_cooldown: f64,
+    /// Added to the score on death
+    pub score: i32,
+    pub behaviour: Behaviour,
+    pub ranged: Option<RangedAttack>,
+    /// Rolled in order when it dies, the first to come up being dropped
+    #[serde(default)]
+    pub drops: Vec<Drop>,
+    /// How likely this enemy is to be picked, relative to the others
+    #[serde(default = "one")]
+    pub spawn_weight: u32,
+}
+
+impl EnemyDef {
+    pub fn tint(&self) -> Color {
+        Color::from_rgba(self.tint[0], self.tint[1], self.tint[2], self.tint[3])
+    }
+}
+
+/// Handle to an enemy archetype, its position in the enemy table.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EnemyKind(pub usize);
 
 #[derive(Clone)]
 pub struct Enemy {
+    /// Unique for the run, handed out by the world as enemies spawn
+    pub id: u32,
+    pub kind: EnemyKind,
     pub position: Vec2,
+    pub prev_position: Vec2,
+    /// Pixels per second
     pub speed: f32,
-    pub texture: Texture2D,
+    pub texture: TextureId,
     pub coll_rect: Rect,
     pub health: i32,
+    /// Health at spawn, after the difficulty is applied
+    pub max_health: i32,
     pub dmg_cd: f64,
     pub can_attack: bool,
+    /// When the last volley went out, for enemies with a ranged attack
+    pub last_shot: f64,
+    pub spawned_at: f64,
+    /// Set for bosses, which move and shoot according to their phase rather than their archetype
+    pub boss: Option<BossId>,
+    /// Index into the boss's phases
+    pub phase: usize,
 }
 
 impl Enemy {
-    pub fn new(position: Vec2, texture: &Texture2D, health: i32) -> Enemy {
+    pub fn new(position: Vec2, kind: EnemyKind, def: &EnemyDef) -> Enemy {
         Enemy {
-            position: position,
-            speed: 1.0,
-            texture: texture.clone(),
-            coll_rect: Rect::new(position.x, position.y, texture.width(), texture.height()),
-            health: health,
+            id: 0,
+            kind,
+            position,
+            prev_position: position,
+            speed: def.speed,
+            texture: def.texture,
+            coll_rect: Rect::new(position.x, position.y, def.size[0], def.size[1]),
+            health: def.health,
+            max_health: def.health,
             dmg_cd: 1.0,
             can_attack: true,
+            last_shot: 0.0,
+            spawned_at: 0.0,
+            boss: None,
+            phase: 0,
         }
     }
 }
+
+fn default_texture() -> TextureId {
+    assets::ENEMY
+}
+
+fn one() -> u32 {
+    1
+}
@@ -0,0 +1,127 @@
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use macroquad::prelude::*;
+
+use crate::level::Level;
+
+/// Cost of a step to a side neighbour. Diagonal steps cost `DIAGONAL_COST`, roughly √2 times as
+/// much, which keeps the sums in whole numbers.
+const STRAIGHT_COST: u32 = 10;
+const DIAGONAL_COST: u32 = 14;
+/// Marks a tile the target can't be reached from
+const UNREACHABLE: u32 = u32::MAX;
+
+/// Neighbour offsets, side neighbours first so they win ties
+const NEIGHBOURS: [(i32, i32); 8] = [
+    (1, 0),
+    (-1, 0),
+    (0, 1),
+    (0, -1),
+    (1, 1),
+    (-1, 1),
+    (1, -1),
+    (-1, -1),
+];
+
+/// Which way to go from every tile in the level to reach one target tile, routing around walls.
+///
+/// Built with one Dijkstra search out from the target and shared by everything heading there, so
+/// a whole horde finds its way for the cost of a single search. The search only reruns when the
+/// target moves to another tile.
+pub struct FlowField {
+    columns: i32,
+    rows: i32,
+    tile_size: Vec2,
+    target: Option<IVec2>,
+    /// Path cost from each tile to the target, row by row
+    cost: Vec<u32>,
+    /// The tile to step to next from each tile, or `None` at the target and where it can't be reached
+    next: Vec<Option<IVec2>>,
+}
+
+impl FlowField {
+    pub fn new(level: &Level) -> FlowField {
+        let tiles = level.columns * level.rows;
+        FlowField {
+            columns: level.columns as i32,
+            rows: level.rows as i32,
+            tile_size: level.tile_size,
+            target: None,
+            cost: vec![UNREACHABLE; tiles],
+            next: vec![None; tiles],
+        }
+    }
+
+    /// Points the field at the tile `target` is in, redoing the search if that's a new tile.
+    pub fn update(&mut self, level: &Level, target: Vec2) {
+        let tile = self.tile_at(target);
+        if self.target == Some(tile) {
+            return;
+        }
+        self.target = Some(tile);
+
+        self.cost.fill(UNREACHABLE);
+        self.next.fill(None);
+        if level.is_solid(tile.x, tile.y) {
+            return;
+        }
+
+        let mut open = BinaryHeap::new();
+        let start = self.index(tile);
+        self.cost[start] = 0;
+        open.push(Reverse((0, tile.y, tile.x)));
+        while let Some(Reverse((cost, y, x))) = open.pop() {
+            let tile = ivec2(x, y);
+            if cost > self.cost[self.index(tile)] {
+                continue;
+            }
+            // Searching out from the target, so each neighbour reached steps back to this tile
+            for (dx, dy) in NEIGHBOURS {
+                let Some(step) = step_cost(level, tile, dx, dy) else {
+                    continue;
+                };
+                let neighbour = tile + ivec2(dx, dy);
+                let index = self.index(neighbour);
+                if cost + step < self.cost[index] {
+                    self.cost[index] = cost + step;
+                    self.next[index] = Some(tile);
+                    open.push(Reverse((cost + step, neighbour.y, neighbour.x)));
+                }
+            }
+        }
+    }
+
+    /// Which way something at `position` should head, or `None` if it is already in the target
+    /// tile or can't get there, where the best it can do is head straight for the target.
+    pub fn heading(&self, position: Vec2) -> Option<Vec2> {
+        let next = self.next[self.index(self.tile_at(position))]?;
+        // Aim for the middle of the next tile, which keeps things off the corners of walls
+        let centre = (next.as_vec2() + Vec2::splat(0.5)) * self.tile_size;
+        Some((centre - position).normalize_or_zero())
+    }
+
+    /// Things off the level are lumped into the edge tiles.
+    fn tile_at(&self, position: Vec2) -> IVec2 {
+        let tile = (position / self.tile_size).floor().as_ivec2();
+        tile.clamp(IVec2::ZERO, ivec2(self.columns - 1, self.rows - 1))
+    }
+
+    fn index(&self, tile: IVec2) -> usize {
+        (tile.y * self.columns + tile.x) as usize
+    }
+}
+
+/// The cost of stepping from `tile` by `dx`, `dy`, or `None` if a wall is in the way. Diagonal
+/// steps need both side tiles open too, so nothing tries to squeeze between two touching corners.
+fn step_cost(level: &Level, tile: IVec2, dx: i32, dy: i32) -> Option<u32> {
+    if level.is_solid(tile.x + dx, tile.y + dy) {
+        return None;
+    }
+    if dx == 0 || dy == 0 {
+        return Some(STRAIGHT_COST);
+    }
+    if level.is_solid(tile.x + dx, tile.y) || level.is_solid(tile.x, tile.y + dy) {
+        return None;
+    }
+    Some(DIAGONAL_COST)
+}
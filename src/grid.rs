@@ -0,0 +1,70 @@
+use macroquad::prelude::*;
+
+/// A uniform grid that buckets entity indices by the cell their collision rect's centre falls in,
+/// so overlap tests only look at entities that are actually nearby.
+///
+/// The grid is rebuilt from scratch each time it is needed; `clear` keeps the cell allocations
+/// around so that doesn't cost anything once the first few ticks have warmed it up.
+pub struct SpatialGrid {
+    cell_size: f32,
+    cols: i32,
+    rows: i32,
+    cells: Vec<Vec<usize>>,
+    /// Half the size of the biggest rect inserted since the last clear
+    reach: Vec2,
+}
+
+impl SpatialGrid {
+    pub fn new(size: Vec2, cell_size: f32) -> SpatialGrid {
+        let cols = (size.x / cell_size).ceil().max(1.0) as i32;
+        let rows = (size.y / cell_size).ceil().max(1.0) as i32;
+        SpatialGrid {
+            cell_size,
+            cols,
+            rows,
+            cells: vec![Vec::new(); (cols * rows) as usize],
+            reach: Vec2::ZERO,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        for cell in self.cells.iter_mut() {
+            cell.clear();
+        }
+        self.reach = Vec2::ZERO;
+    }
+
+    pub fn insert(&mut self, index: usize, rect: &Rect) {
+        let centre = rect.center();
+        let cell = self.cell_index(self.cell_x(centre.x), self.cell_y(centre.y));
+        self.cells[cell].push(index);
+        self.reach = self.reach.max(rect.size() / 2.0);
+    }
+
+    /// Every index whose rect could overlap `rect`, each exactly once.
+    ///
+    /// These are only candidates; callers still do the exact overlap test.
+    pub fn query(&self, rect: &Rect) -> impl Iterator<Item = usize> + '_ {
+        let min_x = self.cell_x(rect.x - self.reach.x);
+        let max_x = self.cell_x(rect.x + rect.w + self.reach.x);
+        let min_y = self.cell_y(rect.y - self.reach.y);
+        let max_y = self.cell_y(rect.y + rect.h + self.reach.y);
+
+        (min_y..=max_y).flat_map(move |y| {
+            (min_x..=max_x).flat_map(move |x| self.cells[self.cell_index(x, y)].iter().copied())
+        })
+    }
+
+    /// Things outside the grid are lumped into the edge cells.
+    fn cell_x(&self, x: f32) -> i32 {
+        ((x / self.cell_size).floor() as i32).clamp(0, self.cols - 1)
+    }
+
+    fn cell_y(&self, y: f32) -> i32 {
+        ((y / self.cell_size).floor() as i32).clamp(0, self.rows - 1)
+    }
+
+    fn cell_index(&self, x: i32, y: i32) -> usize {
+        (y * self.cols + x) as usize
+    }
+}
@@ -0,0 +1,129 @@
+use std::{
+    cmp::Reverse,
+    fs,
+    io::{self, ErrorKind},
+    path::PathBuf,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::settings::Difficulty;
+
+/// How many runs the table keeps.
+pub const MAX_ENTRIES: usize = 10;
+/// Longest name the prompt accepts, in characters.
+pub const MAX_NAME_LEN: usize = 16;
+
+/// One finished run in the table.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ScoreEntry {
+    pub name: String,
+    pub score: i32,
+    /// Seconds survived
+    pub time: f64,
+    /// Name of the weapon fired the most
+    pub weapon: String,
+    pub difficulty: Difficulty,
+    /// Unix time the run ended
+    pub date: u64,
+}
+
+/// The best runs on this machine, best first.
+///
+/// Lives in `highscores.toml` in the user's data directory.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct HighScores {
+    #[serde(default, rename = "entry")]
+    pub entries: Vec<ScoreEntry>,
+}
+
+impl HighScores {
+    /// Reads the table, starting a fresh one if there isn't one yet.
+    ///
+    /// A file that can't be parsed is moved aside to `highscores.toml.bad` rather than being
+    /// overwritten by the next save, so the scores in it can still be dug out by hand.
+    pub fn load() -> HighScores {
+        let Some(path) = scores_path() else {
+            return HighScores::default();
+        };
+
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == ErrorKind::NotFound => return HighScores::default(),
+            Err(err) => {
+                eprintln!("Couldn't read {}: {}", path.display(), err);
+                return HighScores::default();
+            }
+        };
+
+        match toml::from_str::<HighScores>(&text) {
+            Ok(mut scores) => {
+                scores.sort();
+                scores
+            }
+            Err(err) => {
+                eprintln!("Ignoring broken high scores in {}: {}", path.display(), err);
+                if let Err(err) = fs::rename(&path, path.with_extension("toml.bad")) {
+                    eprintln!("Couldn't move {} aside: {}", path.display(), err);
+                }
+                HighScores::default()
+            }
+        }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = scores_path()
+            .ok_or_else(|| io::Error::new(ErrorKind::NotFound, "no data directory"))?;
+        let text = toml::to_string(self).map_err(io::Error::other)?;
+        fs::create_dir_all(path.parent().unwrap())?;
+        fs::write(path, text)
+    }
+
+    /// Whether a run with this score would make it into the table.
+    pub fn qualifies(&self, score: i32) -> bool {
+        self.entries.len() < MAX_ENTRIES
+            || self.entries.last().is_some_and(|last| score > last.score)
+    }
+
+    /// Adds the run in its place, below any run with the same score, and drops whatever falls off
+    /// the bottom.
+    pub fn insert(&mut self, entry: ScoreEntry) {
+        let rank = self
+            .entries
+            .iter()
+            .position(|other| other.score < entry.score);
+        self.entries
+            .insert(rank.unwrap_or(self.entries.len()), entry);
+        self.entries.truncate(MAX_ENTRIES);
+    }
+
+    /// Puts a hand edited file back in order.
+    fn sort(&mut self) {
+        self.entries.sort_by_key(|entry| Reverse(entry.score));
+        self.entries.truncate(MAX_ENTRIES);
+    }
+}
+
+/// `date` as year-month-day, in UTC.
+pub fn format_date(date: u64) -> String {
+    // Days to civil date, from Howard Hinnant's date algorithms
+    let days = (date / 86400) as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+fn scores_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("grillageddon").join("highscores.toml"))
+}
@@ -0,0 +1,457 @@
+use std::collections::BTreeMap;
+
+use macroquad::{input::utils, miniquad, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::canvas;
+
+/// How many weapon slots have a select action.
+pub const WEAPON_SLOTS: usize = 9;
+
+/// Keys that can be bound to an action. Anything else is ignored by the rebinding screen.
+const BINDABLE_KEYS: [KeyCode; 88] = [
+    KeyCode::A,
+    KeyCode::B,
+    KeyCode::C,
+    KeyCode::D,
+    KeyCode::E,
+    KeyCode::F,
+    KeyCode::G,
+    KeyCode::H,
+    KeyCode::I,
+    KeyCode::J,
+    KeyCode::K,
+    KeyCode::L,
+    KeyCode::M,
+    KeyCode::N,
+    KeyCode::O,
+    KeyCode::P,
+    KeyCode::Q,
+    KeyCode::R,
+    KeyCode::S,
+    KeyCode::T,
+    KeyCode::U,
+    KeyCode::V,
+    KeyCode::W,
+    KeyCode::X,
+    KeyCode::Y,
+    KeyCode::Z,
+    KeyCode::Key0,
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+    KeyCode::Kp0,
+    KeyCode::Kp1,
+    KeyCode::Kp2,
+    KeyCode::Kp3,
+    KeyCode::Kp4,
+    KeyCode::Kp5,
+    KeyCode::Kp6,
+    KeyCode::Kp7,
+    KeyCode::Kp8,
+    KeyCode::Kp9,
+    KeyCode::F1,
+    KeyCode::F2,
+    KeyCode::F3,
+    KeyCode::F4,
+    KeyCode::F5,
+    KeyCode::F6,
+    KeyCode::F7,
+    KeyCode::F8,
+    KeyCode::F9,
+    KeyCode::F10,
+    KeyCode::F11,
+    KeyCode::F12,
+    KeyCode::Up,
+    KeyCode::Down,
+    KeyCode::Left,
+    KeyCode::Right,
+    KeyCode::Space,
+    KeyCode::Enter,
+    KeyCode::Tab,
+    KeyCode::Backspace,
+    KeyCode::Escape,
+    KeyCode::LeftShift,
+    KeyCode::RightShift,
+    KeyCode::LeftControl,
+    KeyCode::RightControl,
+    KeyCode::LeftAlt,
+    KeyCode::RightAlt,
+    KeyCode::Comma,
+    KeyCode::Period,
+    KeyCode::Slash,
+    KeyCode::Semicolon,
+    KeyCode::Apostrophe,
+    KeyCode::Minus,
+    KeyCode::Equal,
+    KeyCode::LeftBracket,
+    KeyCode::RightBracket,
+    KeyCode::Backslash,
+    KeyCode::GraveAccent,
+    KeyCode::Insert,
+    KeyCode::Delete,
+    KeyCode::Home,
+    KeyCode::End,
+];
+
+const BINDABLE_BUTTONS: [MouseButton; 3] =
+    [MouseButton::Left, MouseButton::Right, MouseButton::Middle];
+
+/// Something the player can do, which [`Bindings`] maps to keys and mouse buttons.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    Fire,
+    Reload,
+    Pause,
+    /// Weapon slot, counting from zero
+    SelectWeapon(usize),
+    /// Confirms a menu prompt, like the name for a high score
+    Accept,
+    ReplayPause,
+    ReplaySpeed,
+    /// Runs one tick of a paused replay
+    ReplayStep,
+}
+
+impl Action {
+    /// Every action, in the order the controls screen lists them.
+    pub const ALL: [Action; 11 + WEAPON_SLOTS] = [
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::Fire,
+        Action::Reload,
+        Action::Pause,
+        Action::SelectWeapon(0),
+        Action::SelectWeapon(1),
+        Action::SelectWeapon(2),
+        Action::SelectWeapon(3),
+        Action::SelectWeapon(4),
+        Action::SelectWeapon(5),
+        Action::SelectWeapon(6),
+        Action::SelectWeapon(7),
+        Action::SelectWeapon(8),
+        Action::Accept,
+        Action::ReplayPause,
+        Action::ReplaySpeed,
+        Action::ReplayStep,
+    ];
+
+    /// What the action is called in the settings file.
+    fn key(self) -> String {
+        match self {
+            Action::MoveUp => "move_up".to_owned(),
+            Action::MoveDown => "move_down".to_owned(),
+            Action::MoveLeft => "move_left".to_owned(),
+            Action::MoveRight => "move_right".to_owned(),
+            Action::Fire => "fire".to_owned(),
+            Action::Reload => "reload".to_owned(),
+            Action::Pause => "pause".to_owned(),
+            Action::SelectWeapon(slot) => format!("select_weapon_{}", slot + 1),
+            Action::Accept => "accept".to_owned(),
+            Action::ReplayPause => "replay_pause".to_owned(),
+            Action::ReplaySpeed => "replay_speed".to_owned(),
+            Action::ReplayStep => "replay_step".to_owned(),
+        }
+    }
+
+    /// What the action is called on the controls screen.
+    pub fn label(self) -> String {
+        match self {
+            Action::MoveUp => "Move up".to_owned(),
+            Action::MoveDown => "Move down".to_owned(),
+            Action::MoveLeft => "Move left".to_owned(),
+            Action::MoveRight => "Move right".to_owned(),
+            Action::Fire => "Fire".to_owned(),
+            Action::Reload => "Reload".to_owned(),
+            Action::Pause => "Pause".to_owned(),
+            Action::SelectWeapon(slot) => format!("Weapon {}", slot + 1),
+            Action::Accept => "Accept".to_owned(),
+            Action::ReplayPause => "Replay pause".to_owned(),
+            Action::ReplaySpeed => "Replay speed".to_owned(),
+            Action::ReplayStep => "Replay step".to_owned(),
+        }
+    }
+
+    fn index(self) -> usize {
+        Action::ALL
+            .iter()
+            .position(|&action| action == self)
+            .unwrap()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+}
+
+impl Binding {
+    /// Whatever bindable key or mouse button went down this frame.
+    pub fn pressed_this_frame() -> Option<Binding> {
+        if let Some(&button) = BINDABLE_BUTTONS
+            .iter()
+            .find(|&&button| is_mouse_button_pressed(button))
+        {
+            return Some(Binding::Mouse(button));
+        }
+        get_last_key_pressed()
+            .filter(|key| BINDABLE_KEYS.contains(key))
+            .map(Binding::Key)
+    }
+
+    /// How the binding is written in the settings file and shown on screen.
+    pub fn name(self) -> String {
+        match self {
+            Binding::Key(key) => format!("{:?}", key),
+            Binding::Mouse(button) => format!("Mouse{:?}", button),
+        }
+    }
+
+    fn parse(name: &str) -> Option<Binding> {
+        let buttons = BINDABLE_BUTTONS
+            .iter()
+            .map(|&button| Binding::Mouse(button));
+        let keys = BINDABLE_KEYS.iter().map(|&key| Binding::Key(key));
+        buttons.chain(keys).find(|binding| binding.name() == name)
+    }
+
+    fn is_down(self) -> bool {
+        match self {
+            Binding::Key(key) => is_key_down(key),
+            Binding::Mouse(button) => is_mouse_button_down(button),
+        }
+    }
+
+    fn is_pressed(self) -> bool {
+        match self {
+            Binding::Key(key) => is_key_pressed(key),
+            Binding::Mouse(button) => is_mouse_button_pressed(button),
+        }
+    }
+}
+
+/// Which keys and mouse buttons trigger each [`Action`].
+///
+/// Saved with the rest of the settings as a table of action names to lists of key names.
+/// Actions missing from the file keep their default bindings and names that aren't recognised
+/// are dropped.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(
+    from = "BTreeMap<String, Vec<String>>",
+    into = "BTreeMap<String, Vec<String>>"
+)]
+pub struct Bindings {
+    /// Indexed like `Action::ALL`
+    bindings: Vec<Vec<Binding>>,
+}
+
+impl Default for Bindings {
+    fn default() -> Bindings {
+        const WEAPON_KEYS: [KeyCode; WEAPON_SLOTS] = [
+            KeyCode::Key1,
+            KeyCode::Key2,
+            KeyCode::Key3,
+            KeyCode::Key4,
+            KeyCode::Key5,
+            KeyCode::Key6,
+            KeyCode::Key7,
+            KeyCode::Key8,
+            KeyCode::Key9,
+        ];
+
+        let bindings = Action::ALL
+            .iter()
+            .map(|&action| match action {
+                Action::MoveUp => vec![Binding::Key(KeyCode::W), Binding::Key(KeyCode::Up)],
+                Action::MoveDown => vec![Binding::Key(KeyCode::S), Binding::Key(KeyCode::Down)],
+                Action::MoveLeft => vec![Binding::Key(KeyCode::A), Binding::Key(KeyCode::Left)],
+                Action::MoveRight => vec![Binding::Key(KeyCode::D), Binding::Key(KeyCode::Right)],
+                Action::Fire => vec![Binding::Mouse(MouseButton::Left)],
+                Action::Reload => vec![Binding::Key(KeyCode::R)],
+                Action::Pause => vec![Binding::Key(KeyCode::Escape)],
+                Action::SelectWeapon(slot) => vec![Binding::Key(WEAPON_KEYS[slot])],
+                Action::Accept => vec![Binding::Key(KeyCode::Enter)],
+                Action::ReplayPause => vec![Binding::Key(KeyCode::Space)],
+                Action::ReplaySpeed => vec![Binding::Key(KeyCode::F)],
+                Action::ReplayStep => vec![Binding::Key(KeyCode::Period)],
+            })
+            .collect();
+        Bindings { bindings }
+    }
+}
+
+impl Bindings {
+    pub fn get(&self, action: Action) -> &[Binding] {
+        &self.bindings[action.index()]
+    }
+
+    /// Whatever triggers `action`, for showing on screen, or "-" when nothing does.
+    pub fn names(&self, action: Action) -> String {
+        let names: Vec<String> = self
+            .get(action)
+            .iter()
+            .map(|binding| binding.name())
+            .collect();
+        if names.is_empty() {
+            "-".to_owned()
+        } else {
+            names.join(", ")
+        }
+    }
+
+    /// Makes `binding` the only thing triggering `action`, taking it away from any other action.
+    pub fn set(&mut self, action: Action, binding: Binding) {
+        for bindings in self.bindings.iter_mut() {
+            bindings.retain(|&other| other != binding);
+        }
+        self.bindings[action.index()] = vec![binding];
+    }
+
+    pub fn down(&self, action: Action) -> bool {
+        self.get(action).iter().any(|binding| binding.is_down())
+    }
+
+    pub fn pressed(&self, action: Action) -> bool {
+        self.get(action).iter().any(|binding| binding.is_pressed())
+    }
+}
+
+impl From<BTreeMap<String, Vec<String>>> for Bindings {
+    fn from(table: BTreeMap<String, Vec<String>>) -> Bindings {
+        let mut bindings = Bindings::default();
+        for (index, action) in Action::ALL.iter().enumerate() {
+            if let Some(names) = table.get(&action.key()) {
+                bindings.bindings[index] = names
+                    .iter()
+                    .filter_map(|name| Binding::parse(name))
+                    .collect();
+            }
+        }
+        bindings
+    }
+}
+
+impl From<Bindings> for BTreeMap<String, Vec<String>> {
+    fn from(bindings: Bindings) -> BTreeMap<String, Vec<String>> {
+        Action::ALL
+            .iter()
+            .zip(bindings.bindings)
+            .map(|(action, bindings)| {
+                (
+                    action.key(),
+                    bindings.iter().map(|binding| binding.name()).collect(),
+                )
+            })
+            .collect()
+    }
+}
+
+/// Everything the simulation needs to know about the player's controls for one tick.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Input {
+    pub up: bool,
+    pub down: bool,
+    pub left: bool,
+    pub right: bool,
+    /// Weapon slot, counting from zero
+    pub select_weapon: Option<usize>,
+    pub fire_pressed: bool,
+    pub fire_down: bool,
+    pub reload_pressed: bool,
+    pub aim: Vec2,
+}
+
+impl Input {
+    /// Reads the current keyboard and mouse state from macroquad, through the player's bindings.
+    ///
+    /// `view` is where the top left of the canvas is in the world, so the aim comes out in world
+    /// pixels.
+    pub fn capture(bindings: &Bindings, view: Vec2) -> Input {
+        let select_weapon =
+            (0..WEAPON_SLOTS).find(|&slot| bindings.pressed(Action::SelectWeapon(slot)));
+
+        let mouse_pos = canvas::screen_to_canvas(mouse_position().into()) + view;
+        Input {
+            up: bindings.down(Action::MoveUp),
+            down: bindings.down(Action::MoveDown),
+            left: bindings.down(Action::MoveLeft),
+            right: bindings.down(Action::MoveRight),
+            select_weapon,
+            fire_pressed: bindings.pressed(Action::Fire),
+            fire_down: bindings.down(Action::Fire),
+            reload_pressed: bindings.pressed(Action::Reload),
+            aim: mouse_pos,
+        }
+    }
+
+    /// Folds a freshly captured frame into input that is still waiting for a tick.
+    ///
+    /// Held keys simply take their latest value, but one-shot presses are kept until a tick has
+    /// consumed them, so a click is neither lost on a frame that runs no ticks nor repeated on a
+    /// frame that runs several.
+    pub fn latch(&mut self, frame: Input) {
+        let fire_pressed = self.fire_pressed || frame.fire_pressed;
+        let reload_pressed = self.reload_pressed || frame.reload_pressed;
+        let select_weapon = frame.select_weapon.or(self.select_weapon);
+        *self = frame;
+        self.fire_pressed = fire_pressed;
+        self.reload_pressed = reload_pressed;
+        self.select_weapon = select_weapon;
+    }
+
+    /// Forgets the one-shot presses once a tick has seen them.
+    pub fn clear_presses(&mut self) {
+        self.fire_pressed = false;
+        self.reload_pressed = false;
+        self.select_weapon = None;
+    }
+}
+
+/// Keeps track of whether the window has focus, which miniquad reports as the window being
+/// minimised and restored.
+pub struct Focus {
+    subscriber: usize,
+    pub focused: bool,
+}
+
+impl Focus {
+    pub fn new() -> Focus {
+        Focus {
+            subscriber: utils::register_input_subscriber(),
+            focused: true,
+        }
+    }
+
+    /// Catches up on the window events since the last frame. Must be called every frame.
+    pub fn update(&mut self) {
+        let subscriber = self.subscriber;
+        utils::repeat_all_miniquad_input(self, subscriber);
+    }
+}
+
+impl miniquad::EventHandler for Focus {
+    fn update(&mut self) {}
+
+    fn draw(&mut self) {}
+
+    fn window_minimized_event(&mut self) {
+        self.focused = false;
+    }
+
+    fn window_restored_event(&mut self) {
+        self.focused = true;
+    }
+}
@@ -0,0 +1,320 @@
+use std::{fs, path::Path};
+
+use macroquad::prelude::*;
+use serde::Deserialize;
+
+use crate::assets::{Assets, TextureId};
+use crate::pickup::PickupKind;
+
+/// Tiled keeps the flip and rotation flags in the top bits of each tile
+const TILE_FLAGS: u32 = 0xe0000000;
+/// Pixels. Keeps a box resting flush against a tile from counting as overlapping it when float
+/// rounding puts its edge a hair over.
+const EDGE_TOLERANCE: f32 = 0.01;
+
+/// A map made in Tiled and exported as JSON, with CSV tile layer data and embedded tilesets.
+///
+/// Every visible tile layer is drawn, bottom to top. Any tile in the layer called `collision`
+/// blocks the player, enemies and bullets, and so does everything outside the map. Objects with
+/// the type `player_start` or `spawn_point` place those, and ones with the type `pickup` place the
+/// pickup they're named after. Anything else is left alone.
+pub struct Level {
+    /// Pixels
+    pub size: Vec2,
+    /// Pixels
+    pub tile_size: Vec2,
+    /// Map width in tiles
+    pub columns: usize,
+    /// Map height in tiles
+    pub rows: usize,
+    /// The visible tile layers bottom to top, each holding global tile ids row by row with 0 for
+    /// an empty tile
+    pub layers: Vec<Vec<u32>>,
+    pub tilesets: Vec<Tileset>,
+    /// Whether each tile blocks movement, row by row
+    pub solid: Vec<bool>,
+    /// Middle of where the player starts
+    pub player_start: Vec2,
+    /// Middle of each place enemies come from
+    pub spawn_points: Vec<Vec2>,
+    pub pickups: Vec<MapPickup>,
+}
+
+/// A pickup placed on the map.
+pub struct MapPickup {
+    /// Name from `assets/pickups.toml`
+    pub name: String,
+    /// Looked up from `name` when the content is loaded
+    pub kind: Option<PickupKind>,
+    /// Middle of the pickup
+    pub position: Vec2,
+}
+
+/// One tileset image, cut into tiles left to right, top to bottom.
+pub struct Tileset {
+    pub first_gid: u32,
+    pub texture: TextureId,
+    pub columns: u32,
+    pub tile_size: Vec2,
+    pub margin: f32,
+    pub spacing: f32,
+}
+
+#[derive(Deserialize)]
+struct MapFile {
+    width: usize,
+    height: usize,
+    tilewidth: f32,
+    tileheight: f32,
+    #[serde(default)]
+    infinite: bool,
+    layers: Vec<LayerFile>,
+    tilesets: Vec<TilesetFile>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum LayerFile {
+    TileLayer {
+        name: String,
+        #[serde(default)]
+        data: Vec<u32>,
+        visible: bool,
+    },
+    ObjectGroup {
+        objects: Vec<ObjectFile>,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize)]
+struct ObjectFile {
+    /// Called class in some versions of Tiled
+    #[serde(default, rename = "type", alias = "class")]
+    kind: String,
+    #[serde(default)]
+    name: String,
+    x: f32,
+    y: f32,
+    #[serde(default)]
+    width: f32,
+    #[serde(default)]
+    height: f32,
+}
+
+#[derive(Deserialize)]
+struct TilesetFile {
+    firstgid: u32,
+    /// Set instead of everything else when the tileset is kept in its own file
+    source: Option<String>,
+    #[serde(default)]
+    image: String,
+    #[serde(default)]
+    columns: u32,
+    #[serde(default)]
+    tilewidth: f32,
+    #[serde(default)]
+    tileheight: f32,
+    #[serde(default)]
+    margin: f32,
+    #[serde(default)]
+    spacing: f32,
+}
+
+impl Level {
+    /// Reads the map at `path`, registering its tileset images with `assets`.
+    ///
+    /// Like the other data files, a broken map panics with the path and what is wrong with it.
+    pub fn load(path: &str, assets: &mut Assets) -> Level {
+        let text = fs::read_to_string(path).unwrap_or_else(|err| panic!("{}: {}", path, err));
+        let map: MapFile =
+            serde_json::from_str(&text).unwrap_or_else(|err| panic!("{}: {}", path, err));
+        if map.infinite {
+            panic!("{}: infinite maps aren't supported", path);
+        }
+
+        // Tileset images are named relative to the map
+        let folder = Path::new(path).parent().unwrap_or(Path::new(""));
+        let mut tilesets = Vec::new();
+        for tileset in map.tilesets {
+            if let Some(source) = tileset.source {
+                panic!(
+                    "{}: tileset {} needs to be embedded in the map",
+                    path, source
+                );
+            }
+            let image = folder.join(&tileset.image);
+            tilesets.push(Tileset {
+                first_gid: tileset.firstgid,
+                texture: assets.register(&image.to_string_lossy()),
+                columns: tileset.columns.max(1),
+                tile_size: vec2(tileset.tilewidth, tileset.tileheight),
+                margin: tileset.margin,
+                spacing: tileset.spacing,
+            });
+        }
+        // So a tile's tileset is the last one starting at or below its id
+        tilesets.sort_by_key(|tileset| tileset.first_gid);
+
+        let tile_size = vec2(map.tilewidth, map.tileheight);
+        let mut layers = Vec::new();
+        let mut solid = vec![false; map.width * map.height];
+        let mut player_start = vec2(map.width as f32, map.height as f32) * tile_size / 2.0;
+        let mut spawn_points = Vec::new();
+        let mut pickups = Vec::new();
+        for layer in map.layers {
+            match layer {
+                LayerFile::TileLayer {
+                    name,
+                    data,
+                    visible,
+                } => {
+                    if data.len() != map.width * map.height {
+                        panic!("{}: layer {} needs to be saved as CSV", path, name);
+                    }
+                    let tiles: Vec<u32> = data.iter().map(|gid| gid & !TILE_FLAGS).collect();
+                    if name == "collision" {
+                        for (solid, &gid) in solid.iter_mut().zip(tiles.iter()) {
+                            *solid |= gid != 0;
+                        }
+                    }
+                    if visible {
+                        layers.push(tiles);
+                    }
+                }
+                LayerFile::ObjectGroup { objects } => {
+                    for object in objects {
+                        let centre = vec2(
+                            object.x + object.width / 2.0,
+                            object.y + object.height / 2.0,
+                        );
+                        match object.kind.as_str() {
+                            "player_start" => player_start = centre,
+                            "spawn_point" => spawn_points.push(centre),
+                            "pickup" => pickups.push(MapPickup {
+                                name: object.name,
+                                kind: None,
+                                position: centre,
+                            }),
+                            _ => {}
+                        }
+                    }
+                }
+                LayerFile::Other => {}
+            }
+        }
+
+        let level = Level {
+            size: vec2(map.width as f32, map.height as f32) * tile_size,
+            tile_size,
+            columns: map.width,
+            rows: map.height,
+            layers,
+            tilesets,
+            solid,
+            player_start,
+            spawn_points,
+            pickups,
+        };
+
+        if level.spawn_points.is_empty() {
+            panic!("{}: at least one spawn_point object is needed", path);
+        }
+        let pickups = level.pickups.iter().map(|pickup| &pickup.position);
+        for &point in level
+            .spawn_points
+            .iter()
+            .chain([&level.player_start])
+            .chain(pickups)
+        {
+            if level.is_solid_at(point) {
+                panic!(
+                    "{}: the object at {}, {} is inside a wall",
+                    path, point.x, point.y
+                );
+            }
+        }
+        level
+    }
+
+    /// Whether the tile at `column`, `row` blocks movement. Everything off the map does.
+    pub fn is_solid(&self, column: i32, row: i32) -> bool {
+        if column < 0 || row < 0 || column as usize >= self.columns || row as usize >= self.rows {
+            return true;
+        }
+        self.solid[row as usize * self.columns + column as usize]
+    }
+
+    pub fn is_solid_at(&self, point: Vec2) -> bool {
+        let tile = (point / self.tile_size).floor();
+        self.is_solid(tile.x as i32, tile.y as i32)
+    }
+
+    /// Whether any part of `rect` is over a solid tile. Lying flush against one doesn't count.
+    pub fn overlaps_solid(&self, rect: &Rect) -> bool {
+        let (first, last) = self.tiles_under(rect);
+        (first.y..=last.y).any(|row| (first.x..=last.x).any(|column| self.is_solid(column, row)))
+    }
+
+    /// Moves a `size` box at `position` by `delta`, one axis at a time, stopping it flush against
+    /// any solid tile in the way. Returns where it ends up.
+    ///
+    /// Assumes the box moves less than a tile, which anything going under a few thousand pixels a
+    /// second at 60 ticks a second does. A box that somehow started out in a wall moves freely
+    /// until it's out, rather than being stuck there for good.
+    pub fn slide(&self, position: Vec2, size: Vec2, delta: Vec2) -> Vec2 {
+        if self.overlaps_solid(&Rect::new(position.x, position.y, size.x, size.y)) {
+            return position + delta;
+        }
+        let mut position = position;
+
+        position.x += delta.x;
+        if self.overlaps_solid(&Rect::new(position.x, position.y, size.x, size.y)) {
+            position.x = if delta.x > 0.0 {
+                ((position.x + size.x) / self.tile_size.x).floor() * self.tile_size.x - size.x
+            } else {
+                (position.x / self.tile_size.x).floor() * self.tile_size.x + self.tile_size.x
+            };
+        }
+
+        position.y += delta.y;
+        if self.overlaps_solid(&Rect::new(position.x, position.y, size.x, size.y)) {
+            position.y = if delta.y > 0.0 {
+                ((position.y + size.y) / self.tile_size.y).floor() * self.tile_size.y - size.y
+            } else {
+                (position.y / self.tile_size.y).floor() * self.tile_size.y + self.tile_size.y
+            };
+        }
+
+        position
+    }
+
+    /// First and last column and row `rect` covers.
+    pub fn tiles_under(&self, rect: &Rect) -> (IVec2, IVec2) {
+        let first = ((rect.point() + Vec2::splat(EDGE_TOLERANCE)) / self.tile_size).floor();
+        let last =
+            ((rect.point() + rect.size() - Vec2::splat(EDGE_TOLERANCE)) / self.tile_size).floor();
+        (first.as_ivec2(), last.as_ivec2().max(first.as_ivec2()))
+    }
+
+    /// The tileset `gid` comes from and where the tile is in its image, or `None` for an empty tile.
+    pub fn tile_source(&self, gid: u32) -> Option<(&Tileset, Rect)> {
+        let tileset = self
+            .tilesets
+            .iter()
+            .rev()
+            .find(|tileset| tileset.first_gid <= gid)?;
+        let index = gid - tileset.first_gid;
+        let cell = vec2(
+            (index % tileset.columns) as f32,
+            (index / tileset.columns) as f32,
+        );
+        let corner =
+            Vec2::splat(tileset.margin) + cell * (tileset.tile_size + Vec2::splat(tileset.spacing));
+        Some((
+            tileset,
+            Rect::new(corner.x, corner.y, tileset.tile_size.x, tileset.tile_size.y),
+        ))
+    }
+}
@@ -1,65 +1,165 @@
+mod assets;
+mod audio;
+mod bench;
+mod boss;
 mod bullet;
+mod canvas;
+mod content;
 mod enemy;
+mod flow;
+mod grid;
+mod highscore;
+mod input;
+mod level;
+mod pickup;
 mod player;
+mod power_up;
+mod replay;
+mod settings;
+mod wave;
+mod weapon;
+mod world;
+use std::{
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
 use libm::atan2;
 use macroquad::{
-    miniquad::window::quit, prelude::*, ui::{hash, root_ui, widgets, Skin}
-};
-use quad_snd::{
-    decoder::read_wav_ext,
-    mixer::{PlaybackStyle, SoundMixer},
-    mixer::{Sound, Volume},
+    miniquad::window::quit, prelude::*, ui::{hash, root_ui, widgets, Id, Skin, Ui}
 };
 
-use bullet::Bullet;
-use enemy::Enemy;
-use player::{Player, PowerUpType, WeaponType};
-
+use assets::{Assets, TextureId};
+use audio::{Audio, ClipId, SoundType};
+use bullet::Faction;
+use canvas::{Canvas, FollowCamera, CANVAS_SIZE};
+use content::Content;
+use highscore::{HighScores, ScoreEntry, MAX_NAME_LEN};
+use input::{Action, Binding, Bindings, Focus, Input, WEAPON_SLOTS};
+use level::Level;
+use replay::{Playback, Replay};
+use settings::{Difficulty, Settings, RESOLUTIONS};
+use wave::WavePhase;
+use world::{Event, World, BLAST_TIME, BOSS_INTRO_TIME, TIME_STEP};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum GameState {
     Menu,
     Play,
+    Paused,
     Options,
-    Over
+    Controls,
+    Info,
+    HighScores,
+    Over,
+    Replay,
+    /// Picking a replay to watch
+    Replays,
 }
 
-pub enum SoundType {
-    MenuClick,
-    PistolShot,
-    EnemyHit,
-    MenuMusic,
-    PlayerDie
+/// Options passed on the command line.
+#[derive(Default)]
+struct Args {
+    /// Play every run with this seed instead of a fresh random one
+    seed: Option<u64>,
+    /// Open straight into playback of this replay file
+    replay: Option<PathBuf>,
+    /// Time the simulation under load instead of playing
+    bench: bool,
 }
 
-const MAX_ENEMIES: usize = 1000;
+/// Longest frame we will try to catch up on, so a stall doesn't queue up hundreds of ticks.
+const MAX_FRAME_TIME: f32 = 0.25;
+
+/// Seconds the "Wave N" announcement stays up once a wave starts.
+const WAVE_BANNER_TIME: f64 = 2.0;
+/// Seconds a boss's name stays up when it arrives or is beaten
+const BOSS_BANNER_TIME: f64 = BOSS_INTRO_TIME;
+const BOSS_BAR_SIZE: Vec2 = Vec2::new(500.0, 18.0);
+const POWER_UP_BAR_SIZE: Vec2 = Vec2::new(140.0, 8.0);
+const RELOAD_BAR_SIZE: Vec2 = Vec2::new(100.0, 10.0);
+const INVENTORY_SLOT_SIZE: Vec2 = Vec2::new(44.0, 40.0);
+const LOCKED_WEAPON_TINT: Color = Color::new(0.1, 0.1, 0.1, 0.35);
+/// Seconds the news of a weapon unlocking stays up
+const UNLOCK_TOAST_TIME: f64 = 2.5;
+/// Seconds before a dropped pickup vanishes that it starts blinking
+const PICKUP_BLINK_TIME: f64 = 3.0;
+
+/// Seconds the screen shakes for after the player is hit, and at the big moments of a boss fight.
+const SHAKE_TIME: f32 = 0.2;
+/// Pixels the screen is thrown around by at the start of a shake.
+const SHAKE_STRENGTH: f32 = 6.0;
+
+/// The sounds the data files name, loaded once at startup.
+struct ContentSounds {
+    weapons: Vec<WeaponSounds>,
+    /// Intro and defeat sound for each boss
+    bosses: Vec<(ClipId, ClipId)>,
+    pickups: Vec<ClipId>,
+}
+
+struct WeaponSounds {
+    shot: ClipId,
+    dry_fire: ClipId,
+    reload: ClipId,
+}
 
 pub struct Game {
     state: GameState,
-    player: Player,
-    enemies: Vec<Enemy>,
-    enemy_texture: Texture2D,
-    bullets: Vec<Bullet>,
-    spawn_point: Vec<SpawnPoint>,
-    last_spawn: f64,
-    spawn_rate: f64,
-    ui_assets: Vec<Texture2D>,
+    args: Args,
+    settings: Settings,
+    world: World,
+    input: Input,
+    /// Play pauses itself whenever the window loses focus
+    focus: Focus,
+    accumulator: f32,
+    recording: Replay,
+    playback: Option<Playback>,
+    /// The replays listed on the replays screen, newest first
+    replays: Vec<PathBuf>,
+    content: Rc<Content>,
+    assets: Assets,
+    canvas: Canvas,
+    camera: FollowCamera,
+    /// Indexed by weapon
+    weapon_icons: Vec<TextureId>,
+    sounds: ContentSounds,
     ui_skin: Skin,
-    score: i32,
+    /// What `ui_skin` was built for, see [`canvas::ui_scale`]
+    ui_scale: f32,
     final_score: i32,
-    power_up_timer: f32,
-    can_get_powerup: bool
+    final_seed: u64,
+    final_wave: u32,
+    high_scores: HighScores,
+    /// The run that just ended, while the player types a name for the high score table
+    pending_score: Option<ScoreEntry>,
+    name_input: String,
+    /// Seconds of screen shake left
+    shake: f32,
+    /// The action on the controls screen waiting for a key
+    rebinding: Option<Action>,
+    /// Where the options screen goes back to
+    options_return: GameState,
     // play_music: bool
 }
 
-fn window_conf() -> Conf {
+fn window_conf(settings: &Settings) -> Conf {
     Conf {
         window_title: "Grillageddon".to_owned(),
-        window_width: 800,
-        window_height: 600,
-        window_resizable: false,
+        window_width: settings.resolution[0] as i32,
+        window_height: settings.resolution[1] as i32,
+        fullscreen: settings.fullscreen,
+        window_resizable: true,
         ..Default::default()
     }
 }
-fn create_ui_skin() -> Skin {
+/// The menu look, with text and spacing sized for the canvas drawn `scale` times its size.
+fn create_ui_skin(scale: f32) -> Skin {
+    let font_size = |size: f32| (size * scale).round() as u16;
+    let margin = |left: f32, right: f32, top: f32, bottom: f32| {
+        RectOffset::new(left * scale, right * scale, top * scale, bottom * scale)
+    };
     let skin1: Skin = {
         let label_style = root_ui()
             .style_builder()
@@ -68,11 +168,11 @@ fn create_ui_skin() -> Skin {
                     .unwrap(),
             )
             .background_margin(RectOffset::new(37.0, 37.0, 5.0, 5.0))
-            .margin(RectOffset::new(10.0, 10.0, 0.0, 0.0))
+            .margin(margin(10.0, 10.0, 0.0, 0.0))
             .font(include_bytes!("../assets/ui/HTOWERT.TTF"))
             .unwrap()
             .text_color(Color::from_rgba(0, 0, 0, 255))
-            .font_size(30)
+            .font_size(font_size(30.0))
             .build();
 
         let window_style = root_ui()
@@ -82,7 +182,7 @@ fn create_ui_skin() -> Skin {
                     .unwrap(),
             )
             .background_margin(RectOffset::new(20.0, 20.0, 10.0, 10.0))
-            .margin(RectOffset::new(-20.0, -30.0, 0.0, 0.0))
+            .margin(margin(-20.0, -30.0, 0.0, 0.0))
             .build();
 
         let button_style = root_ui()
@@ -92,7 +192,7 @@ fn create_ui_skin() -> Skin {
                     .unwrap(),
             )
             .background_margin(RectOffset::new(37.0, 37.0, 5.0, 5.0))
-            .margin(RectOffset::new(10.0, 10.0, 15.0, 0.0))
+            .margin(margin(10.0, 10.0, 15.0, 0.0))
             .background_hovered(
                 Image::from_file_with_format(include_bytes!("../assets/ui/b_hover.png"), None)
                     .unwrap(),
@@ -104,706 +204,1289 @@ fn create_ui_skin() -> Skin {
             .font(include_bytes!("../assets/ui/HTOWERT.TTF"))
             .unwrap()
             .text_color(Color::from_rgba(0, 0, 0, 255))
-            .font_size(40)
+            .font_size(font_size(40.0))
+            .build();
+
+        // The name entry on the game over screen, otherwise macroquad's own
+        let editbox_style = root_ui()
+            .style_builder()
+            .text_color(Color::from_rgba(0, 0, 0, 255))
+            .color_selected(Color::from_rgba(200, 200, 200, 255))
+            .font_size(font_size(16.0))
             .build();
 
         Skin {
             window_style,
             button_style,
             label_style,
+            editbox_style,
             ..root_ui().default_skin()
         }
     };
-    return skin1;
+    skin1
 }
 
 
-pub fn sound_load(bytes: &[u8], style: PlaybackStyle) -> Sound {
-    let sound = read_wav_ext(bytes, style).unwrap();
-    return sound;
-}
-
-fn sound_play(sound: SoundType, volume: Volume, mixer: &mut SoundMixer) {
-    match sound {
-        SoundType::MenuClick => {
-            let sound = sound_load(include_bytes!("../assets/sounds/button_click.wav"), PlaybackStyle::Once);
-            mixer.play_ext(sound, volume);
-        },
-        SoundType::PistolShot => {
-            let sound = sound_load(include_bytes!("../assets/sounds/gun_shoot.wav"), PlaybackStyle::Once);
-            mixer.play_ext(sound, volume);
-        },
-        SoundType::EnemyHit => {
-            let sound = sound_load(include_bytes!("../assets/sounds/enemy_hit.wav"), PlaybackStyle::Once);
-            mixer.play_ext(sound, volume);
-        },
-        SoundType::MenuMusic => {
-            let sound = sound_load(include_bytes!("../assets/sounds/menu_music.wav"), PlaybackStyle::Once);
-            mixer.play_ext(sound, volume);
-        },
-        SoundType::PlayerDie => {
-            let sound = sound_load(include_bytes!("../assets/sounds/player_die.wav"), PlaybackStyle::Once);
-            mixer.play_ext(sound, volume);
-        }
+fn main() {
+    let args = parse_args();
+    if args.bench {
+        bench::run();
+        return;
     }
+    // Loaded before the window opens, which needs the size and fullscreen setting
+    let settings = Settings::load();
+    macroquad::Window::from_config(window_conf(&settings), run(args, settings));
 }
 
+async fn run(args: Args, settings: Settings) {
+    let mut audio = Audio::new();
+    audio.apply_settings(&settings);
+    let mut game = init_game(args, settings, &mut audio).await;
 
-#[macroquad::main(window_conf)]
-async fn main() {
-    let mut game = init_game().await;
-    let mut mixer = SoundMixer::new();
+    if let Some(path) = game.args.replay.clone() {
+        start_playback(&mut game, &path);
+    }
 
     loop {
-        clear_background(WHITE);
+        game.focus.update();
+        clear_background(BLACK);
         match game.state {
             GameState::Play => {
-                update(&mut game, &mut mixer).await;
-                draw(&mut game);
+                update(&mut game, &mut audio);
+                draw(&game, &game.world, game.accumulator / TIME_STEP);
+                game.canvas.present();
+            }
+            GameState::Paused => {
+                draw(&game, &game.world, game.accumulator / TIME_STEP);
+                game.canvas.present();
+                draw_rectangle(0.0, 0.0, screen_width(), screen_height(), Color::new(0.0, 0.0, 0.0, 0.5));
+                pause_menu(&mut game, &mut audio);
             }
-            GameState::Menu => menu(&mut game, &mut mixer).await,
-            GameState::Options => menu(&mut game, &mut mixer).await,
-            GameState::Over => menu(&mut game, &mut mixer).await
+            GameState::Replay => {
+                update_playback(&mut game, &mut audio);
+                draw_playback(&game);
+                game.canvas.present();
+            }
+            GameState::Menu => menu(&mut game, &mut audio).await,
+            GameState::Options => menu(&mut game, &mut audio).await,
+            GameState::Controls => menu(&mut game, &mut audio).await,
+            GameState::Info => menu(&mut game, &mut audio).await,
+            GameState::HighScores => menu(&mut game, &mut audio).await,
+            GameState::Replays => menu(&mut game, &mut audio).await,
+            GameState::Over => menu(&mut game, &mut audio).await,
         }
         next_frame().await;
     }
 }
 
-async fn init_game() -> Game {
-    let ui_skin = create_ui_skin();
-    let player_texture = load_texture("assets/player.png").await.unwrap();
-    let player = Player::new(
-        Vec2::new(screen_width() / 2.0, screen_height() / 2.0),
-        3.0,
-        player_texture,
-    );
-
-    let enemy_texture = load_texture("assets/enemy.png").await.unwrap();
-    let enemies: Vec<Enemy> = Vec::new();
-
-    let bullets: Vec<Bullet> = Vec::new();
-
-    let spawn_point_texture = load_texture("assets/spawn_point.png").await.unwrap();
-    let mut spawn_points: Vec<SpawnPoint> = Vec::new();
-    spawn_points.push(SpawnPoint {
-        pos: Vec2::new(10.0, 10.0),
-        texture: spawn_point_texture.clone(),
-    });
-    spawn_points.push(SpawnPoint {
-        pos: Vec2::new(750.0, 10.0),
-        texture: spawn_point_texture.clone(),
-    });
-    spawn_points.push(SpawnPoint {
-        pos: Vec2::new(10.0, 550.0),
-        texture: spawn_point_texture.clone(),
-    });
-    spawn_points.push(SpawnPoint {
-        pos: Vec2::new(750.0, 550.0),
-        texture: spawn_point_texture.clone(),
-    });
-
-    let pistol_textrue = load_texture("assets/pistol.png").await.unwrap();
-    let shotgun_texture = load_texture("assets/shotgun.png").await.unwrap();
-    let machinegun_texture = load_texture("assets/machine_gun.png").await.unwrap();
+fn parse_args() -> Args {
+    let mut args = Args::default();
+    let mut iter = std::env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--seed" => match iter.next().map(|value| value.parse::<u64>()) {
+                Some(Ok(seed)) => args.seed = Some(seed),
+                _ => eprintln!("--seed expects a whole number, ignoring it"),
+            },
+            "--replay" => args.replay = iter.next().map(PathBuf::from),
+            "--bench" => args.bench = true,
+            _ => eprintln!("Unknown argument {}", arg),
+        }
+    }
+    args
+}
 
-    let mut assets = Vec::new();
-    assets.push(pistol_textrue);
-    assets.push(shotgun_texture);
-    assets.push(machinegun_texture);
+async fn init_game(args: Args, settings: Settings, audio: &mut Audio) -> Game {
+    let ui_scale = canvas::ui_scale();
+    let ui_skin = create_ui_skin(ui_scale);
+    let mut assets = Assets::new();
+    let content = Rc::new(Content::load(&mut assets));
+
+    let weapon_icons = content.weapons.iter().map(|weapon| assets.register(&weapon.icon)).collect();
+    let sounds = ContentSounds {
+        weapons: content
+            .weapons
+            .iter()
+            .map(|weapon| WeaponSounds {
+                shot: audio.load(&weapon.sound),
+                dry_fire: audio.load(&weapon.dry_fire_sound),
+                reload: audio.load(&weapon.reload_sound),
+            })
+            .collect(),
+        bosses: content
+            .bosses
+            .iter()
+            .map(|boss| (audio.load(&boss.intro_sound), audio.load(&boss.defeat_sound)))
+            .collect(),
+        pickups: content.pickups.iter().map(|pickup| audio.load(&pickup.sound)).collect(),
+    };
+    assets.load().await;
 
+    let world = new_world(&args, settings.difficulty, &content);
+    let recording = Replay::new(world.seed, world.difficulty);
+    let mut camera = FollowCamera::new();
+    camera.snap(world.player.coll_rect.center(), world.size);
     Game {
         state: GameState::Menu,
-        player: player,
-        enemies: enemies,
-        enemy_texture: enemy_texture,
-        bullets: bullets,
-        spawn_point: spawn_points,
-        last_spawn: get_time(),
-        spawn_rate: 0.5,
-        ui_assets: assets,
-        ui_skin: ui_skin,
-        score: 0,
+        args,
+        settings,
+        world,
+        input: Input::default(),
+        focus: Focus::new(),
+        accumulator: 0.0,
+        recording,
+        playback: None,
+        replays: Vec::new(),
+        content,
+        assets,
+        canvas: Canvas::new(),
+        camera,
+        weapon_icons,
+        sounds,
+        ui_skin,
+        ui_scale,
         final_score: 0,
-        power_up_timer: 0.0,
-        can_get_powerup: true,
+        final_seed: 0,
+        final_wave: 0,
+        high_scores: HighScores::load(),
+        pending_score: None,
+        name_input: String::new(),
+        shake: 0.0,
+        rebinding: None,
+        options_return: GameState::Menu,
         // play_music: true
     }
 
 }
 
-fn spawn_enemies(game: &mut Game) {
-    let spawn_timer = get_time();
-    if spawn_timer - game.last_spawn > game.spawn_rate && game.enemies.len() < MAX_ENEMIES {
-        let spawn_point = &game.spawn_point[rand::gen_range(0, game.spawn_point.len())];
-        let enemy_pos = spawn_point.pos;
-        game.enemies
-            .push(Enemy::new(enemy_pos, &game.enemy_texture, 10));
-        game.last_spawn = spawn_timer;
-    }
-}
-
-fn reset_game(game: &mut Game){
-    game.score = 0;
-    game.bullets.clear();
-    game.enemies.clear();
-    game.player.is_dead = false;
-    game.player.health = 500;
-    game.player.position = Vec2::new(screen_width()/2.0, screen_height()/2.0);
+fn new_world(args: &Args, difficulty: Difficulty, content: &Rc<Content>) -> World {
+    let seed = args.seed.unwrap_or_else(::rand::random);
+    World::new(seed, difficulty, content.clone())
 }
 
-async fn update(game: &mut Game, mixer: &mut SoundMixer) {
-    if is_key_pressed(KeyCode::Escape) {
-        game.state = GameState::Menu;
-    }
-
-    if game.player.is_dead {
-        reset_game(game);
-        game.state = GameState::Over
+fn update(game: &mut Game, audio: &mut Audio) {
+    if game.settings.bindings.pressed(Action::Pause) || !game.focus.focused {
+        game.state = GameState::Paused;
+        game.shake = 0.0;
+        return;
     }
 
-    if !game.player.is_dead{
-        spawn_enemies(game);
-        player_update(game, mixer);
-        bullet_update(game, mixer).await;
-        enemy_update(game);
-        collision_check(game, mixer);
-    }
-}
+    game.input.latch(Input::capture(&game.settings.bindings, game.camera.position));
+    game.accumulator += get_frame_time().min(MAX_FRAME_TIME);
+    game.shake = (game.shake - get_frame_time()).max(0.0);
 
-async fn bullet_update(game: &mut Game, mixer: &mut SoundMixer) {
+    while game.accumulator >= TIME_STEP {
+        game.accumulator -= TIME_STEP;
+        let events = game.world.tick(&game.input, TIME_STEP);
+        game.recording.inputs.push(game.input);
+        game.input.clear_presses();
 
-    match game.player.weapon_type {
-        WeaponType::Pistol => {
-            if is_mouse_button_pressed(MouseButton::Left) {
-                sound_play(SoundType::PistolShot,Volume(0.3), mixer);
-                let mouse_pos = mouse_position();
-                let mouse_target = Vec2::new(mouse_pos.0, mouse_pos.1);
-                game.bullets.push(
-                    Bullet::new(
-                        Vec2::new(game.player.position.x, game.player.position.y + 16.),
-                        mouse_target,
-                        true,
-                        5.0,
-                    )
-                    .await,
-                )
-            }
+        for event in events {
+            handle_event(game, event, audio);
         }
-        WeaponType::Machine => {
-            let current_time = get_time();
-            if is_mouse_button_down(MouseButton::Left)
-                && current_time - game.player.last_shot > game.player.fire_rate
-            {
-                sound_play(SoundType::PistolShot,Volume(0.3), mixer);
-                let mouse_pos = mouse_position();
-                let mouse_target = Vec2::new(mouse_pos.0, mouse_pos.1);
-                game.bullets.push(
-                    Bullet::new(
-                        Vec2::new(game.player.position.x, game.player.position.y + 16.),
-                        mouse_target,
-                        true,
-                        7.0,
-                    )
-                    .await,
-                );
-                game.player.last_shot = current_time;
-            }
-        }
-        WeaponType::Shotgun => {
-            let current_time = get_time();
-            if is_mouse_button_down(MouseButton::Left)
-                && current_time - game.player.last_shot > game.player.shotgun_fire_rate
-            {
-                sound_play(SoundType::PistolShot,Volume(0.3), mixer);
-                let player_pos = Vec2::new(game.player.position.x, game.player.position.y + 16.);
-                let mouse_pos = mouse_position();
-                let mouse_target = Vec2::new(mouse_pos.0, mouse_pos.1);
-                let spread_angle: f64 = 20.0;
-
-                let mouse_direction = (mouse_target - player_pos).normalize(); // Calculate direction to mouse
-                let base_angle = mouse_direction.y.atan2(mouse_direction.x); // Calculate base angle
-
-                let spread_increment = spread_angle.to_radians() / (3 - 1) as f64;
-
-                for i in 0..3 {
-                    let angle = base_angle
-                        + (-spread_angle.to_radians() as f32 / 2.0
-                            + spread_increment as f32 * i as f32);
-                    let bullet_direction = Vec2::new(angle.cos() as f32, angle.sin() as f32);
-                    let bullet_target = player_pos + bullet_direction * 100.0;
-                    game.bullets
-                        .push(Bullet::new(player_pos, bullet_target, true, 7.0).await);
-                }
 
-                game.player.last_shot = current_time
-            }
+        if let GameState::Over = game.state {
+            game.accumulator = 0.0;
+            break;
         }
     }
 
-    for bullet in game.bullets.iter_mut() {
-        bullet.position += bullet.velocity * bullet.speed;
+    follow_player(&mut game.camera, &game.world);
+}
 
-        if bullet.position.x > screen_width() || bullet.position.x < 0.0 {
-            bullet.is_active = false;
-        }
+fn follow_player(camera: &mut FollowCamera, world: &World) {
+    let dt = get_frame_time().min(MAX_FRAME_TIME);
+    camera.follow(world.player.coll_rect.center(), world.size, dt);
+}
 
-        if bullet.position.y > screen_height() || bullet.position.y < 0.0 {
-            bullet.is_active = false;
-        }
+fn handle_event(game: &mut Game, event: Event, audio: &mut Audio) {
+    play_event_sound(&event, &game.sounds, audio);
 
-        bullet.coll_rect.x = bullet.position.x;
-        bullet.coll_rect.y = bullet.position.y;
+    let big_moment = matches!(
+        event,
+        Event::PlayerHit | Event::BossArrived { .. } | Event::BossPhase { .. } | Event::BossDefeated { .. }
+    );
+    if big_moment && game.settings.screen_shake {
+        game.shake = SHAKE_TIME;
     }
 
-    game.bullets.retain(|bullet| bullet.is_active);
-}
-
-fn collision_check(game: &mut Game, mixer: &mut SoundMixer) {
-    for enemy in game.enemies.iter_mut() {
-        for bullet in game.bullets.iter_mut() {
-            if enemy.coll_rect.overlaps(&bullet.coll_rect) {
-                bullet.is_active = false;
-                let dmg: i32;
-                match game.player.weapon_type {
-                    WeaponType::Pistol =>       dmg = game.player.damage,
-                    WeaponType::Machine =>      dmg = game.player.damage - 2,
-                    WeaponType::Shotgun =>      dmg = game.player.damage
-                }
-                damage_enemy(enemy, dmg);
-                sound_play(SoundType::EnemyHit, Volume(0.2), mixer);
-            }
+    if let Event::PlayerDied { score } = event {
+        game.final_score = score;
+        game.final_seed = game.world.seed;
+        game.final_wave = game.world.waves.number;
+        if game.high_scores.qualifies(score) {
+            game.pending_score = Some(score_entry(game, score));
         }
-    }
-
-    for enemy in game.enemies.iter_mut() {
-        let current_time = get_time();
 
-        if enemy.coll_rect.overlaps(&game.player.coll_rect) && enemy.can_attack{
-            game.player.health -= 10;
-            enemy.can_attack = false;
-            enemy.dmg_cd = current_time;
-            sound_play(SoundType::EnemyHit, Volume(0.2), mixer);
-        }
-        // reset can attack to true after a few seconds
-        if !enemy.can_attack && (current_time - enemy.dmg_cd as f64) > 0.5 {
-            enemy.can_attack = true
+        game.recording.checksum = game.world.checksum();
+        if let Err(err) = game.recording.save() {
+            eprintln!("Couldn't save replay: {}", err);
         }
+
+        reset_run(game);
+        game.state = GameState::Over
     }
 }
 
-fn damage_enemy(enemy: &mut Enemy, dmg: i32) {
-    enemy.health -= dmg;
+/// Throws the run in progress away and sets up a fresh one.
+fn reset_run(game: &mut Game) {
+    game.world = new_world(&game.args, game.settings.difficulty, &game.content);
+    game.recording = Replay::new(game.world.seed, game.world.difficulty);
+    game.input = Input::default();
+    game.accumulator = 0.0;
+    game.shake = 0.0;
+    game.camera.snap(game.world.player.coll_rect.center(), game.world.size);
 }
 
-fn player_update(game: &mut Game, mixer: &mut SoundMixer) {
-    if is_key_pressed(KeyCode::Key1) {
-        game.player.weapon_type = WeaponType::Pistol
-    }
-
-    if is_key_pressed(KeyCode::Key2) {
-        game.player.weapon_type = WeaponType::Machine
+/// A high score table entry for the run in `game.world`, still without a name.
+fn score_entry(game: &Game, score: i32) -> ScoreEntry {
+    let world = &game.world;
+    // Ties go to the weapon earlier in the list, which is the starting weapon if nothing was fired
+    let weapon = (0..world.shots.len()).rev().max_by_key(|&index| world.shots[index]).unwrap_or(0);
+    let date = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or(0);
+
+    ScoreEntry {
+        name: String::new(),
+        score,
+        time: world.time,
+        weapon: game.content.weapons[weapon].name.clone(),
+        difficulty: world.difficulty,
+        date,
     }
+}
 
-    if is_key_pressed(KeyCode::Key3) {
-        game.player.weapon_type = WeaponType::Shotgun
+fn play_event_sound(event: &Event, sounds: &ContentSounds, audio: &mut Audio) {
+    match event {
+        Event::Shot { weapon } => audio.play(sounds.weapons[weapon.0].shot),
+        Event::DryFire { weapon } => audio.play(sounds.weapons[weapon.0].dry_fire),
+        Event::ReloadStarted { weapon } => audio.play(sounds.weapons[weapon.0].reload),
+        // The new gun being racked
+        Event::WeaponUnlocked { weapon } => audio.play(sounds.weapons[weapon.0].reload),
+        Event::EnemyHit => audio.play(SoundType::EnemyHit),
+        Event::EnemyKilled => {},
+        Event::PlayerHit => audio.play(SoundType::EnemyHit),
+        Event::PlayerDied { .. } => audio.play(SoundType::PlayerDie),
+        Event::WaveStarted { .. } => {},
+        Event::WaveCleared { .. } => {},
+        // The boss roars again each time it gets angrier
+        Event::BossArrived { boss } | Event::BossPhase { boss, .. } => audio.play(sounds.bosses[boss.0].0),
+        Event::BossDefeated { boss } => audio.play(sounds.bosses[boss.0].1),
+        Event::PickedUp { pickup } => audio.play(sounds.pickups[pickup.0]),
+        Event::Exploded => audio.play(SoundType::Explosion),
     }
+}
 
-    if is_key_down(KeyCode::A) {
-        game.player.velocity.x -= game.player.acceleration;
+fn start_playback(game: &mut Game, path: &Path) {
+    match Replay::load(path) {
+        Ok(replay) => {
+            let playback = Playback::new(replay, game.content.clone());
+            game.camera.snap(playback.world.player.coll_rect.center(), playback.world.size);
+            game.playback = Some(playback);
+            game.state = GameState::Replay;
+        }
+        Err(err) => eprintln!("Couldn't load replay {}: {}", path.display(), err),
     }
+}
 
-    if is_key_down(KeyCode::D) {
-        game.player.velocity.x += game.player.acceleration;
-    }
+fn update_playback(game: &mut Game, audio: &mut Audio) {
+    let Some(playback) = game.playback.as_mut() else {
+        game.state = GameState::Menu;
+        return;
+    };
 
-    if is_key_down(KeyCode::W) {
-        game.player.velocity.y -= game.player.acceleration;
+    let bindings = &game.settings.bindings;
+    if bindings.pressed(Action::Pause) {
+        game.playback = None;
+        game.state = GameState::Menu;
+        return;
     }
 
-    if is_key_down(KeyCode::S) {
-        game.player.velocity.y += game.player.acceleration;
+    if bindings.pressed(Action::ReplayPause) {
+        playback.paused = !playback.paused;
     }
 
-    game.player.velocity.y = clamp(game.player.velocity.y, -game.player.speed, game.player.speed);
-    game.player.velocity.x = clamp(game.player.velocity.x, -game.player.speed, game.player.speed);
-
-    if game.player.velocity.x > 0.0 {
-        game.player.velocity.x -= game.player.friction;
-    } else if game.player.velocity.x < 0.0 {
-        game.player.velocity.x += game.player.friction; 
+    if bindings.pressed(Action::ReplaySpeed) {
+        playback.speed = match playback.speed {
+            1 => 2,
+            2 => 4,
+            _ => 1,
+        };
     }
 
-    if game.player.velocity.y > 0.0 {
-        game.player.velocity.y -= game.player.friction;
-    } else if game.player.velocity.y < 0.0 {
-        game.player.velocity.y += game.player.friction; 
+    let mut ticks = 0;
+    if playback.paused {
+        // Step one tick at a time while paused
+        if bindings.pressed(Action::ReplayStep) {
+            ticks = 1;
+        }
+    } else {
+        playback.accumulator += get_frame_time().min(MAX_FRAME_TIME) * playback.speed as f32;
+        while playback.accumulator >= TIME_STEP {
+            playback.accumulator -= TIME_STEP;
+            ticks += 1;
+        }
     }
 
-    if game.player.health <= 0 {
-        game.final_score = game.score;
-        game.player.is_dead = true;
-        sound_play(SoundType::PlayerDie, Volume(0.3), mixer)
+    for _ in 0..ticks {
+        if playback.finished() {
+            break;
+        }
+        for event in playback.step() {
+            play_event_sound(&event, &game.sounds, audio);
+        }
     }
 
-    player_powerups(game);
+    follow_player(&mut game.camera, &playback.world);
+}
 
-    
-    game.player.position += game.player.velocity;
-    game.player.coll_rect.x = game.player.position.x;
-    game.player.coll_rect.y = game.player.position.y;
-    
-    bounds_check(game);
+fn draw_playback(game: &Game) {
+    let Some(playback) = game.playback.as_ref() else {
+        return;
+    };
 
-}
+    let alpha = if playback.paused || playback.finished() {
+        1.0
+    } else {
+        playback.accumulator / TIME_STEP
+    };
+    draw(game, &playback.world, alpha);
 
-fn bounds_check(game: &mut Game) {
-    if game.player.position.x >= screen_width()  - game.player.texture.width() {
-        game.player.position.x = screen_width() - game.player.texture.width()
+    let mut status = format!(
+        "Replay x{}  {}/{}",
+        playback.speed,
+        playback.tick,
+        playback.replay.inputs.len()
+    );
+    if playback.paused {
+        status += "  Paused";
     }
-    if game.player.position.x <= 0.0 {
-        game.player.position.x = 0.0
+    if playback.desynced() {
+        status += "  Desynced!";
+    } else if playback.finished() {
+        status += "  End";
     }
 
-    if game.player.position.y > screen_height()  - (game.player.texture.height() - 10.0) {
-        game.player.position.y = screen_height() - (game.player.texture.height() - 10.0)
-    }
-    if game.player.position.y <= 0.0 {
-        game.player.position.y = 0.0
+    let params = TextParams {
+        font_size: 30,
+        color: BLACK,
+        ..Default::default()
+    };
+    draw_text_ex(&status, 10.0, 80.0, params.clone());
+    let bindings = &game.settings.bindings;
+    let controls = format!(
+        "{} pause  {} speed  {} step  {} back",
+        bindings.names(Action::ReplayPause),
+        bindings.names(Action::ReplaySpeed),
+        bindings.names(Action::ReplayStep),
+        bindings.names(Action::Pause)
+    );
+    draw_text_ex(&controls, 10.0, 105.0, params.clone());
+    if playback.replay.version != env!("CARGO_PKG_VERSION") {
+        let warning = "Recorded with version ".to_owned() + &playback.replay.version;
+        draw_text_ex(&warning, 10.0, 130.0, params);
     }
 }
 
-fn enemy_update(game: &mut Game) {
-    let player_pos: Vec2 = game.player.position;
-
-    // Clone the enemies vector to iterate over
-    let enemies_clone = game.enemies.clone();
+/// Draws `world` onto the canvas, with `alpha` saying how far we are between its last tick and
+/// the next one. Drawing is left pointed at the canvas so callers can add to it before presenting.
+fn draw(game: &Game, world: &World, alpha: f32) {
+    let player_pos = world.player.prev_position.lerp(world.player.position, alpha);
 
-    for enemy in game.enemies.iter_mut() {
-        // Calculate the direction towards the player
-        let direction = player_pos - enemy.position;
-        let distance = direction.length();
-
-        // Normalize the direction
-        let mut normalized_direction = direction;
-        if distance != 0.0 {
-            normalized_direction /= distance;
-        }
+    let mut shake = Vec2::ZERO;
+    if game.shake > 0.0 {
+        // Fades out over the length of the shake
+        let strength = SHAKE_STRENGTH * game.shake / SHAKE_TIME;
+        shake = vec2(rand::gen_range(-1.0, 1.0), rand::gen_range(-1.0, 1.0)) * strength;
+    }
+    game.canvas.begin(game.camera.position + shake);
+    clear_background(DARKGRAY);
+    draw_level(game, &world.content.level);
 
-        // Check for collisions with other enemies and adjust position
-        for other_enemy in enemies_clone.iter() {
-            if enemy.coll_rect.overlaps(&other_enemy.coll_rect) {
-                let avoidance_direction = enemy.position - other_enemy.position;
-                let avoidance_distance = avoidance_direction.length();
+    for point in world.spawn_points.iter() {
+        draw_texture(game.assets.texture(point.texture), point.pos.x, point.pos.y, WHITE);
+    }
 
-                if avoidance_distance != 0.0 {
-                    // Adjust the normalized direction based on avoidance direction
-                    normalized_direction += avoidance_direction.normalize() / avoidance_distance;
-                }
+    for pickup in world.pickups.iter() {
+        // Blinks for its last few seconds
+        if let Some(at) = pickup.expires_at {
+            let left = at - world.time;
+            if left < PICKUP_BLINK_TIME && (left * 8.0) as i64 % 2 == 0 {
+                continue;
             }
         }
-        if enemy.health <= 0 {
-            game.score += 5;
-        }
-        enemy.position += normalized_direction * enemy.speed;
-        enemy.coll_rect.x = enemy.position.x;
-        enemy.coll_rect.y = enemy.position.y;
-    }
-
-    game.enemies.retain(|enemy| enemy.health > 0);
-}
-
-fn draw(game: &mut Game) {
-    draw_text_ex(&game.score.to_string(), screen_width()/2.0, 50.0, TextParams{
-        font_size: 50,
-        color: BLACK,
-        ..Default::default()
-    });
-    // Draw the three guns at the bottom
-    draw_inventory(game);
-    draw_hud(game);
-
-    for point in game.spawn_point.iter() {
-        draw_texture(&point.texture, point.pos.x, point.pos.y, WHITE);
+        let def = &world.content.pickups[pickup.kind.0];
+        let size = Vec2::from(def.size);
+        let corner = pickup.position - size / 2.0;
+        draw_texture_ex(
+            game.assets.texture(def.texture),
+            corner.x,
+            corner.y,
+            def.tint(),
+            DrawTextureParams {
+                dest_size: Some(size),
+                ..Default::default()
+            },
+        );
     }
 
-    for bullet in game.bullets.iter_mut() {
+    for bullet in world.bullets.iter() {
+        let bullet_pos = bullet.prev_position.lerp(bullet.position, alpha);
+        // Enemy fire stands out from the player's own
+        let (outline, fill) = match bullet.owner {
+            Faction::Player if bullet.projectile.blast_radius > 0.0 => (ORANGE, BLACK),
+            Faction::Player => (RED, BLACK),
+            Faction::Enemy => (PURPLE, MAGENTA),
+        };
         draw_rectangle_lines(
-            bullet.position.x,
-            bullet.position.y,
+            bullet_pos.x,
+            bullet_pos.y,
             bullet.coll_rect.w,
             bullet.coll_rect.h,
             2.,
-            RED,
+            outline,
         );
-        draw_texture(&bullet.texture, bullet.position.x, bullet.position.y, BLACK);
+        draw_texture(game.assets.texture(bullet.texture), bullet_pos.x, bullet_pos.y, fill);
     }
 
-    for enemy in game.enemies.iter_mut() {
+    for enemy in world.enemies.iter() {
+        let def = &world.content.enemies[enemy.kind.0];
+        let enemy_pos = enemy.prev_position.lerp(enemy.position, alpha);
 
-        let direction = game.player.position - enemy.position;
+        let direction = player_pos - enemy_pos;
         let angle_to_player = atan2(direction.y as f64, direction.x as f64);
         let rotation = angle_to_player;
         draw_texture_ex(
-            &enemy.texture,
-            enemy.position.x,
-            enemy.position.y,
-            GREEN,
+            game.assets.texture(enemy.texture),
+            enemy_pos.x,
+            enemy_pos.y,
+            def.tint(),
             DrawTextureParams {
+                dest_size: Some(enemy.coll_rect.size()),
                 rotation: rotation as f32,
                 ..Default::default()
             },
         )
     }
 
-    let mouse_pos = mouse_position();
-    let mouse_target = Vec2::new(mouse_pos.0, mouse_pos.1);
-    let direction = game.player.position - mouse_target;
+    // Blasts flash up and fade out as they spread
+    for blast in world.blasts.iter() {
+        let progress = ((world.time - blast.at) / BLAST_TIME).clamp(0.0, 1.0) as f32;
+        let radius = blast.radius * (0.6 + 0.4 * progress);
+        draw_circle(blast.position.x, blast.position.y, radius, Color::new(1.0, 0.6, 0.1, 0.6 * (1.0 - progress)));
+        draw_circle_lines(blast.position.x, blast.position.y, radius, 2.0, Color::new(0.8, 0.2, 0.0, 1.0 - progress));
+    }
+
+    let direction = player_pos - world.player.aim;
     let angle_to_mouse = atan2(direction.y as f64, direction.x as f64);
     let rotation = angle_to_mouse;
     draw_texture_ex(
-        &game.player.texture,
-        game.player.position.x,
-        game.player.position.y,
+        game.assets.texture(world.player.texture),
+        player_pos.x,
+        player_pos.y,
         WHITE,
         DrawTextureParams {
             rotation: rotation as f32,
             ..Default::default()
         },
     );
+
+    // The HUD stays put while the world shakes
+    game.canvas.begin(Vec2::ZERO);
+    draw_text_ex(world.score.to_string(), CANVAS_SIZE.x / 2.0, 50.0, TextParams{
+        font_size: 50,
+        color: BLACK,
+        ..Default::default()
+    });
+    // Draw the weapon slots at the bottom
+    draw_inventory(game, world);
+    draw_hud(world);
+}
+
+/// The level's tile layers, only as much of them as the camera can see.
+fn draw_level(game: &Game, level: &Level) {
+    let view = Rect::new(game.camera.position.x, game.camera.position.y, CANVAS_SIZE.x, CANVAS_SIZE.y);
+    let (first, last) = level.tiles_under(&view);
+    let first = first.max(IVec2::ZERO);
+    let last = last.min(ivec2(level.columns as i32 - 1, level.rows as i32 - 1));
+
+    for layer in level.layers.iter() {
+        for row in first.y..=last.y {
+            for column in first.x..=last.x {
+                let gid = layer[row as usize * level.columns + column as usize];
+                let Some((tileset, source)) = level.tile_source(gid) else {
+                    continue;
+                };
+                let position = vec2(column as f32, row as f32) * level.tile_size;
+                draw_texture_ex(
+                    game.assets.texture(tileset.texture),
+                    position.x,
+                    position.y,
+                    WHITE,
+                    DrawTextureParams {
+                        dest_size: Some(level.tile_size),
+                        source: Some(source),
+                        ..Default::default()
+                    },
+                );
+            }
+        }
+    }
 }
 
-fn draw_hud(game: &mut Game) {
-    let hp_text = "HP ".to_owned() + &game.player.health.to_string();
-    draw_text_ex(&hp_text, 50.0, screen_height() - 50.0, TextParams{
+fn draw_hud(world: &World) {
+    let hp_text = "HP ".to_owned() + &world.player.health.to_string();
+    draw_text_ex(&hp_text, 50.0, CANVAS_SIZE.y - 50.0, TextParams{
         font_size: 30,
         color: BLACK,
         ..Default::default()
     });
+
+    let wave_text = "Wave ".to_owned() + &world.waves.number.to_string();
+    draw_text_ex(&wave_text, 50.0, 40.0, TextParams{
+        font_size: 30,
+        color: BLACK,
+        ..Default::default()
+    });
+
+    draw_ammo(world);
+    draw_wave_banner(world);
+    draw_unlock_toast(world);
+    draw_boss_hud(world);
+    draw_power_up_timers(world);
 }
 
-fn draw_inventory(game: &mut Game) {
-    let color: Color;
-    match game.player.power_up {
-        player::PowerUpType::None => color = WHITE,
-        player::PowerUpType::FastAttack => color = GREEN,
-        player::PowerUpType::QuadDamage => color = PURPLE,
+/// What's left for the weapon in hand under the health, or how far along a reload is.
+fn draw_ammo(world: &World) {
+    let def = &world.content.weapons[world.player.weapon.0];
+    let Some(magazine) = def.magazine else {
+        return;
     };
-    match game.player.weapon_type {
-        WeaponType::Pistol => draw_texture_ex(
-            &game.ui_assets[0],
-            screen_width() / 2.0,
-            screen_height() - 50.0,
-            color,
-            DrawTextureParams {
-                ..Default::default()
-            },
-        ),
-        WeaponType::Machine => draw_texture_ex(
-            &game.ui_assets[2],
-            screen_width() / 2.0,
-            screen_height() - 50.0,
-            color,
-            DrawTextureParams {
-                ..Default::default()
-            },
-        ),
-        WeaponType::Shotgun => draw_texture_ex(
-            &game.ui_assets[1],
-            screen_width() / 2.0,
-            screen_height() - 50.0,
-            color,
+    let ammo = world.player.ammo[world.player.weapon.0];
+    let y = CANVAS_SIZE.y - 22.0;
+
+    if let Some(until) = world.player.reloading_until {
+        let fraction = (1.0 - (until - world.time) / def.reload_time.max(f64::EPSILON)) as f32;
+        draw_rectangle(50.0, y - 14.0, RELOAD_BAR_SIZE.x, RELOAD_BAR_SIZE.y, Color::new(0.0, 0.0, 0.0, 0.5));
+        draw_rectangle(50.0, y - 14.0, RELOAD_BAR_SIZE.x * fraction.clamp(0.0, 1.0), RELOAD_BAR_SIZE.y, GOLD);
+        draw_rectangle_lines(50.0, y - 14.0, RELOAD_BAR_SIZE.x, RELOAD_BAR_SIZE.y, 1.0, BLACK);
+        return;
+    }
+
+    let text = match ammo.reserve {
+        Some(reserve) => format!("{} / {}  +{}", ammo.loaded, magazine, reserve),
+        None => format!("{} / {}", ammo.loaded, magazine),
+    };
+    // Red once it's down to the last few, or out altogether
+    let color = if ammo.loaded * 4 <= magazine { RED } else { BLACK };
+    draw_text_ex(&text, 50.0, y, TextParams{
+        font_size: 24,
+        color,
+        ..Default::default()
+    });
+}
+
+/// Every running power-up down the right hand side, each with a bar draining as it runs out and
+/// how many copies are stacked up.
+fn draw_power_up_timers(world: &World) {
+    let mut shown = Vec::new();
+    for active in world.player.power_ups.iter() {
+        if !shown.contains(&active.id) {
+            shown.push(active.id);
+        }
+    }
+
+    let x = CANVAS_SIZE.x - POWER_UP_BAR_SIZE.x - 30.0;
+    let mut y = CANVAS_SIZE.y - 40.0;
+    for id in shown.into_iter().rev() {
+        let def = &world.content.power_ups[id.0];
+        let copies: Vec<_> = world.player.power_ups.iter().filter(|active| active.id == id).collect();
+        let longest = copies.iter().max_by(|a, b| a.expires_at.total_cmp(&b.expires_at)).unwrap();
+        let left = longest.expires_at - world.time;
+        let fraction = (left / (longest.expires_at - longest.started_at)) as f32;
+
+        draw_rectangle(x, y, POWER_UP_BAR_SIZE.x, POWER_UP_BAR_SIZE.y, Color::new(0.0, 0.0, 0.0, 0.5));
+        draw_rectangle(x, y, POWER_UP_BAR_SIZE.x * fraction.clamp(0.0, 1.0), POWER_UP_BAR_SIZE.y, def.tint());
+        draw_rectangle_lines(x, y, POWER_UP_BAR_SIZE.x, POWER_UP_BAR_SIZE.y, 1.0, BLACK);
+
+        let label = match copies.len() {
+            1 => format!("{} {:.1}s", def.name, left),
+            stacks => format!("{} x{} {:.1}s", def.name, stacks, left),
+        };
+        draw_text_ex(&label, x, y - 4.0, TextParams{
+            font_size: 20,
+            color: BLACK,
+            ..Default::default()
+        });
+        y -= 34.0;
+    }
+}
+
+/// The boss's name and health across the top while it's alive, and its name in large letters as
+/// it arrives and again once it's beaten.
+fn draw_boss_hud(world: &World) {
+    let Some(encounter) = world.encounter else {
+        return;
+    };
+    let def = &world.content.bosses[encounter.boss.0];
+
+    let banner = match encounter.defeated_at {
+        Some(at) if world.time - at < BOSS_BANNER_TIME => Some(format!("{} defeated", def.name)),
+        None if world.time - encounter.arrived_at < BOSS_BANNER_TIME => Some(def.name.clone()),
+        _ => None,
+    };
+    if let Some(banner) = banner {
+        let size = measure_text(&banner, None, 70, 1.0);
+        draw_text_ex(&banner, (CANVAS_SIZE.x - size.width) / 2.0, CANVAS_SIZE.y / 2.0, TextParams{
+            font_size: 70,
+            color: MAROON,
+            ..Default::default()
+        });
+    }
+
+    let Some(boss) = world.enemies.iter().find(|enemy| enemy.boss == Some(encounter.boss)) else {
+        return;
+    };
+    let bar = Rect::new((CANVAS_SIZE.x - BOSS_BAR_SIZE.x) / 2.0, 60.0, BOSS_BAR_SIZE.x, BOSS_BAR_SIZE.y);
+    let fraction = (boss.health as f32 / boss.max_health as f32).clamp(0.0, 1.0);
+    draw_rectangle(bar.x, bar.y, bar.w, bar.h, Color::new(0.0, 0.0, 0.0, 0.5));
+    draw_rectangle(bar.x, bar.y, bar.w * fraction, bar.h, RED);
+    // A notch where each phase after the first begins
+    for phase in def.phases.iter().skip(1) {
+        let x = bar.x + bar.w * phase.below;
+        draw_line(x, bar.y, x, bar.y + bar.h, 2.0, BLACK);
+    }
+    draw_rectangle_lines(bar.x, bar.y, bar.w, bar.h, 2.0, BLACK);
+
+    let size = measure_text(&def.name, None, 24, 1.0);
+    draw_text_ex(&def.name, (CANVAS_SIZE.x - size.width) / 2.0, bar.y - 6.0, TextParams{
+        font_size: 24,
+        color: BLACK,
+        ..Default::default()
+    });
+}
+
+/// Counts down to the next wave, then announces it for a moment once it starts.
+fn draw_wave_banner(world: &World) {
+    let banner = match world.waves.phase {
+        WavePhase::Intermission { until } => {
+            format!("Wave {} in {}", world.waves.number, (until - world.time).ceil() as i32)
+        }
+        _ if world.time - world.waves.started_at < WAVE_BANNER_TIME => {
+            format!("Wave {}", world.waves.number)
+        }
+        _ => return,
+    };
+
+    let size = measure_text(&banner, None, 60, 1.0);
+    draw_text_ex(&banner, (CANVAS_SIZE.x - size.width) / 2.0, CANVAS_SIZE.y / 3.0, TextParams{
+        font_size: 60,
+        color: BLACK,
+        ..Default::default()
+    });
+}
+
+/// Every weapon slot along the bottom, the one in hand outlined and the locked ones greyed out,
+/// with the name of the one in hand above.
+fn draw_inventory(game: &Game, world: &World) {
+    // Tinted by the latest power-up picked up
+    let color = world
+        .player
+        .power_ups
+        .iter()
+        .max_by(|a, b| a.started_at.total_cmp(&b.started_at))
+        .map_or(WHITE, |active| world.content.power_ups[active.id.0].tint());
+
+    let count = game.content.weapons.len() as f32;
+    let left = (CANVAS_SIZE.x - count * INVENTORY_SLOT_SIZE.x) / 2.0;
+    let top = CANVAS_SIZE.y - INVENTORY_SLOT_SIZE.y - 10.0;
+    for (index, &icon) in game.weapon_icons.iter().enumerate() {
+        let slot = Rect::new(left + index as f32 * INVENTORY_SLOT_SIZE.x, top, INVENTORY_SLOT_SIZE.x, INVENTORY_SLOT_SIZE.y);
+        let unlocked = world.player.unlocked_at[index].is_some();
+        let tint = match (index == world.player.weapon.0, unlocked) {
+            (true, _) => color,
+            (false, true) => WHITE,
+            (false, false) => LOCKED_WEAPON_TINT,
+        };
+        draw_texture_ex(
+            game.assets.texture(icon),
+            slot.x + (slot.w - 32.0) / 2.0,
+            slot.y + (slot.h - 32.0) / 2.0,
+            tint,
             DrawTextureParams {
+                dest_size: Some(vec2(32.0, 32.0)),
                 ..Default::default()
             },
-        ),
+        );
+        if index == world.player.weapon.0 {
+            draw_rectangle_lines(slot.x + 2.0, slot.y, slot.w - 4.0, slot.h, 2.0, BLACK);
+        }
+        draw_text_ex((index + 1).to_string(), slot.x + 5.0, slot.y + 12.0, TextParams {
+            font_size: 16,
+            color: if unlocked { BLACK } else { GRAY },
+            ..Default::default()
+        });
+    }
+
+    let name = &game.content.weapons[world.player.weapon.0].name;
+    let size = measure_text(name, None, 24, 1.0);
+    draw_text_ex(name, (CANVAS_SIZE.x - size.width) / 2.0, top - 8.0, TextParams {
+        font_size: 24,
+        color: BLACK,
+        ..Default::default()
+    });
+}
+
+/// Announces the weapon unlocked most recently, for a moment after it happens.
+fn draw_unlock_toast(world: &World) {
+    let latest = world
+        .player
+        .unlocked_at
+        .iter()
+        .enumerate()
+        // Weapons there from the start were never unlocked as such
+        .filter(|&(index, _)| world.content.weapons[index].unlock.is_some())
+        .filter_map(|(index, at)| at.map(|at| (index, at)))
+        .max_by(|a, b| a.1.total_cmp(&b.1));
+    let Some((index, at)) = latest else {
+        return;
+    };
+    if world.time - at >= UNLOCK_TOAST_TIME {
+        return;
+    }
+
+    let text = format!("{} unlocked, press {}", world.content.weapons[index].name, index + 1);
+    let size = measure_text(&text, None, 36, 1.0);
+    draw_text_ex(&text, (CANVAS_SIZE.x - size.width) / 2.0, CANVAS_SIZE.y / 3.0 + 50.0, TextParams{
+        font_size: 36,
+        color: DARKGREEN,
+        ..Default::default()
+    });
+}
+
+/// Puts the pending run into the high score table under the name typed in and writes the table out.
+fn save_high_score(game: &mut Game) {
+    let Some(mut entry) = game.pending_score.take() else {
+        return;
+    };
+
+    entry.name = match game.name_input.trim() {
+        "" => "Anonymous".to_owned(),
+        name => name.to_owned(),
+    };
+    game.high_scores.insert(entry);
+    if let Err(err) = game.high_scores.save() {
+        eprintln!("Couldn't save high scores: {}", err);
+    }
+}
+
+/// Puts changed settings into effect straight away.
+fn apply_settings(game: &mut Game, audio: &mut Audio, before: &Settings) {
+    audio.apply_settings(&game.settings);
+
+    if game.settings.fullscreen != before.fullscreen {
+        set_fullscreen(game.settings.fullscreen);
     }
+    if game.settings.resolution != before.resolution && !game.settings.fullscreen {
+        request_new_screen_size(game.settings.resolution[0] as f32, game.settings.resolution[1] as f32);
+    }
+
+    // A run that is already under way keeps the difficulty it started on
+    if game.settings.difficulty != before.difficulty && game.world.time == 0.0 {
+        reset_run(game);
+    }
+}
+
+/// The overlay drawn over the frozen game while paused.
+fn pause_menu(game: &mut Game, audio: &mut Audio) {
+    if game.settings.bindings.pressed(Action::Pause) {
+        game.state = GameState::Play;
+        return;
+    }
+
+    let size = vec2(300.0, 400.0);
+    refresh_ui_skin(game);
+    root_ui().push_skin(&game.ui_skin);
+    widgets::Window::new(hash!(), canvas::menu_origin() + scaled(CANVAS_SIZE - size) / 2.0, scaled(size))
+        .titlebar(false)
+        .movable(false)
+        .ui(&mut root_ui(), |ui| {
+            widgets::Label::new("Paused")
+                .position(scaled(vec2(90.0, 10.0)))
+                .ui(ui);
+
+            let resume_button = widgets::Button::new("Resume")
+                .position(scaled(vec2(70.0, 80.0)))
+                .ui(ui);
+
+            let restart_button = widgets::Button::new("Restart")
+                .position(scaled(vec2(70.0, 150.0)))
+                .ui(ui);
+
+            let options_button = widgets::Button::new("Options")
+                .position(scaled(vec2(70.0, 220.0)))
+                .ui(ui);
+
+            let quit_button = widgets::Button::new("Quit to menu")
+                .position(scaled(vec2(30.0, 290.0)))
+                .ui(ui);
+
+            if resume_button {
+                audio.play(SoundType::MenuClick);
+                game.state = GameState::Play;
+            }
+
+            if restart_button {
+                audio.play(SoundType::MenuClick);
+                reset_run(game);
+                game.state = GameState::Play;
+            }
+
+            if options_button {
+                audio.play(SoundType::MenuClick);
+                game.options_return = GameState::Paused;
+                game.state = GameState::Options;
+            }
+
+            if quit_button {
+                audio.play(SoundType::MenuClick);
+                reset_run(game);
+                game.state = GameState::Menu;
+            }
+        });
+    root_ui().pop_skin();
 }
 
+/// The info screen's line on the controls, read off the current bindings.
+fn controls_summary(bindings: &Bindings, weapons: usize) -> String {
+    let movement = [Action::MoveUp, Action::MoveLeft, Action::MoveDown, Action::MoveRight].map(|action| bindings.names(action));
+    let slots = weapons.clamp(1, WEAPON_SLOTS);
+    let weapons = if slots == 1 {
+        format!("weapon: {}", bindings.names(Action::SelectWeapon(0)))
+    } else {
+        format!(
+            "weapons: {} to {}",
+            bindings.names(Action::SelectWeapon(0)),
+            bindings.names(Action::SelectWeapon(slots - 1))
+        )
+    };
+    format!("Move: {}, {}, reload: {}", movement.join(" "), weapons, bindings.names(Action::Reload))
+}
+
+/// A menu screen's window, covering the canvas, with `f` drawing its widgets.
+fn menu_window(id: Id, f: impl FnOnce(&mut Ui)) {
+    widgets::Window::new(id, canvas::menu_origin() - vec2(5.0, 0.0), scaled(CANVAS_SIZE) + vec2(5.0, 5.0))
+        .titlebar(false)
+        .movable(false)
+        .ui(&mut root_ui(), f);
+}
+
+/// `point`, laid out in canvas pixels, in the window pixels the UI is drawn in.
+fn scaled(point: Vec2) -> Vec2 {
+    point * canvas::ui_scale()
+}
 
-async fn menu(game: &mut Game, mixer: &mut SoundMixer) {
+/// Rebuilds the UI skin if the window has been resized since, so text grows and shrinks along
+/// with the menus.
+fn refresh_ui_skin(game: &mut Game) {
+    let scale = canvas::ui_scale();
+    if scale != game.ui_scale {
+        game.ui_skin = create_ui_skin(scale);
+        game.ui_scale = scale;
+    }
+}
+
+async fn menu(game: &mut Game, audio: &mut Audio) {
+    refresh_ui_skin(game);
     match game.state {
         GameState::Menu => {
             // TODO: This is not a good way to do this.
             // if game.play_music {
-            //     sound_play(SoundType::MenuMusic, Volume(0.2), mixer);
+            //     audio.play(SoundType::MenuMusic);
             //     game.play_music = false
             // }
             root_ui().push_skin(&game.ui_skin);
-            root_ui().window(
-                hash!(),
-                vec2(0.0 - 5., 0.0),
-                vec2(screen_width() + 5., screen_height() + 5.0),
-                |ui| {
-                    widgets::Label::new("Grillageddon")
-                        .position(vec2(260.0, 10.0))
-                        .ui(ui);
-                    let play_button = widgets::Button::new("Play")
-                        .position(vec2(300., 100.0))
-                        .ui(ui);
-                    let info_button = widgets::Button::new("Info")
-                        .position(vec2(300.0, 200.0))
-                        .ui(ui);
+            menu_window(hash!(), |ui| {
+                widgets::Label::new("Grillageddon")
+                    .position(scaled(vec2(260.0, 10.0)))
+                    .ui(ui);
+                let play_button = widgets::Button::new("Play")
+                    .position(scaled(vec2(300., 90.0)))
+                    .ui(ui);
+                let options_button = widgets::Button::new("Options")
+                    .position(scaled(vec2(300.0, 160.0)))
+                    .ui(ui);
 
-                    let quit_button = widgets::Button::new("Quit")
-                        .position(vec2(300.0, 300.0))
-                        .ui(ui);
+                let info_button = widgets::Button::new("Info")
+                    .position(scaled(vec2(300.0, 230.0)))
+                    .ui(ui);
 
-                    if play_button {
-                        sound_play(SoundType::MenuClick, Volume(0.5), mixer);
-                        game.state = GameState::Play;
-                    }
+                let scores_button = widgets::Button::new("High Scores")
+                    .position(scaled(vec2(300.0, 300.0)))
+                    .ui(ui);
 
-                    if info_button {
-                        sound_play(SoundType::MenuClick, Volume(0.5), mixer);
-                        game.state = GameState::Options;
-                    }
+                let replay_button = widgets::Button::new("Replay")
+                    .position(scaled(vec2(300.0, 370.0)))
+                    .ui(ui);
 
-                    if quit_button {
-                        sound_play(SoundType::MenuClick, Volume(0.5), mixer);
-                        quit()
-                    }
-                },
-            );
+                let quit_button = widgets::Button::new("Quit")
+                    .position(scaled(vec2(300.0, 440.0)))
+                    .ui(ui);
+
+                if play_button {
+                    audio.play(SoundType::MenuClick);
+                    game.state = GameState::Play;
+                }
+
+                if options_button {
+                    audio.play(SoundType::MenuClick);
+                    game.options_return = GameState::Menu;
+                    game.state = GameState::Options;
+                }
+
+                if info_button {
+                    audio.play(SoundType::MenuClick);
+                    game.state = GameState::Info;
+                }
+
+                if scores_button {
+                    audio.play(SoundType::MenuClick);
+                    game.state = GameState::HighScores;
+                }
+
+                if replay_button {
+                    audio.play(SoundType::MenuClick);
+                    game.replays = replay::list();
+                    game.state = GameState::Replays;
+                }
+
+                if quit_button {
+                    audio.play(SoundType::MenuClick);
+                    quit()
+                }
+            });
             root_ui().pop_skin();
         }
         GameState::Play => {},
+        GameState::Paused => {},
+        GameState::Replay => {},
         GameState::Options => {
+            let before = game.settings.clone();
+            let resolution_names: Vec<String> = RESOLUTIONS
+                .iter()
+                .map(|size| format!("{}x{}", size[0], size[1]))
+                .collect();
+            let resolution_names: Vec<&str> = resolution_names.iter().map(|name| name.as_str()).collect();
+            let difficulty_names = Difficulty::ALL.map(|difficulty| difficulty.name());
+
             root_ui().push_skin(&game.ui_skin);
-            root_ui().window(
-                hash!(),
-                vec2(0.0 - 5., 0.0),
-                vec2(screen_width() + 5., screen_height() + 5.0),
-                |ui| {
-                    widgets::Label::new("Grillageddon")
-                        .position(vec2(260.0, 10.0))
-                        .ui(ui);
+            menu_window(hash!(), |ui| {
+                widgets::Label::new("Options")
+                    .position(scaled(vec2(300.0, 10.0)))
+                    .ui(ui);
 
-                    widgets::Label::new(
-                        "Controls: WASD cycle through weapons: 1, 2, 3",
-                    )
-                    .position(vec2(100.0, 100.0))
+                let settings = &mut game.settings;
+                widgets::Group::new(hash!(), scaled(vec2(600.0, 320.0)))
+                    .position(scaled(vec2(100.0, 80.0)))
+                    .ui(ui, |ui| {
+                        widgets::Slider::new(hash!(), 0.0..1.0)
+                            .label("Master")
+                            .ui(ui, &mut settings.master_volume);
+                        // Saved for when there's music to play; nothing reads it yet
+                        widgets::Slider::new(hash!(), 0.0..1.0)
+                            .label("Music (none yet)")
+                            .ui(ui, &mut settings.music_volume);
+                        widgets::Slider::new(hash!(), 0.0..1.0)
+                            .label("Effects")
+                            .ui(ui, &mut settings.sfx_volume);
+
+                        widgets::Checkbox::new(hash!())
+                            .label("Fullscreen")
+                            .ui(ui, &mut settings.fullscreen);
+
+                        let mut resolution = RESOLUTIONS
+                            .iter()
+                            .position(|size| *size == settings.resolution)
+                            .unwrap_or(0);
+                        widgets::ComboBox::new(hash!(), &resolution_names)
+                            .label("Resolution")
+                            .ui(ui, &mut resolution);
+                        settings.resolution = RESOLUTIONS[resolution];
+
+                        widgets::Checkbox::new(hash!())
+                            .label("Screen shake")
+                            .ui(ui, &mut settings.screen_shake);
+
+                        let mut difficulty = settings.difficulty as usize;
+                        widgets::ComboBox::new(hash!(), &difficulty_names)
+                            .label("Difficulty")
+                            .ui(ui, &mut difficulty);
+                        settings.difficulty = Difficulty::ALL[difficulty];
+                    });
+
+                let controls_button = widgets::Button::new("Controls")
+                    .position(scaled(vec2(200., 450.0)))
                     .ui(ui);
 
-                    widgets::Label::new(
-                        "You get a random powerup at the start of the game"
-                    )
-                    .position(vec2(100.0, 200.0))
+                let back_button = widgets::Button::new("Back")
+                    .position(scaled(vec2(420., 450.0)))
                     .ui(ui);
 
-                    widgets::Label::new(
-                        "And a new one every 50 points, They are represented by color"
-                    )
-                    .position(vec2(-20.0, 230.0))
+                if controls_button {
+                    audio.play(SoundType::MenuClick);
+                    game.state = GameState::Controls
+                }
+
+                if back_button {
+                    audio.play(SoundType::MenuClick);
+                    if let Err(err) = game.settings.save() {
+                        eprintln!("Couldn't save settings: {}", err);
+                    }
+                    game.state = game.options_return
+                }
+            });
+            root_ui().pop_skin();
+
+            if game.settings != before {
+                apply_settings(game, audio, &before);
+            }
+        }
+        GameState::Controls => {
+            if let Some(action) = game.rebinding {
+                if let Some(binding) = Binding::pressed_this_frame() {
+                    game.settings.bindings.set(action, binding);
+                    game.rebinding = None;
+                }
+            }
+
+            root_ui().push_skin(&game.ui_skin);
+            menu_window(hash!(), |ui| {
+                let title = match game.rebinding {
+                    Some(action) => format!("Press a key for {}", action.label()),
+                    None => "Click an action to rebind it".to_owned(),
+                };
+                widgets::Label::new(title)
+                    .position(scaled(vec2(100.0, 10.0)))
                     .ui(ui);
 
-                    widgets::Label::new(
-                        "Green: Fast fire, Purple: Quad Damage"
-                    )
-                    .position(vec2(100.0, 290.0))
+                // Scrolls once the list is longer than the group
+                widgets::Group::new(hash!(), scaled(vec2(600.0, 360.0)))
+                    .position(scaled(vec2(100.0, 70.0)))
+                    .ui(ui, |ui| {
+                        for action in Action::ALL {
+                            let text = format!("{}: {}", action.label(), game.settings.bindings.names(action));
+
+                            if widgets::Button::new(text).ui(ui) {
+                                audio.play(SoundType::MenuClick);
+                                game.rebinding = Some(action);
+                            }
+                        }
+                    });
+
+                let defaults_button = widgets::Button::new("Defaults")
+                    .position(scaled(vec2(200., 450.0)))
                     .ui(ui);
 
-                    let back_button = widgets::Button::new("Back")
-                        .position(vec2(300., 400.0))
-                        .ui(ui);
+                let back_button = widgets::Button::new("Back")
+                    .position(scaled(vec2(420., 450.0)))
+                    .ui(ui);
 
-                    if back_button {
-                        sound_play(SoundType::MenuClick, Volume(0.5), mixer);
-                        game.state = GameState::Menu
+                if defaults_button {
+                    audio.play(SoundType::MenuClick);
+                    game.settings.bindings = Default::default();
+                    game.rebinding = None;
+                }
+
+                if back_button {
+                    audio.play(SoundType::MenuClick);
+                    if let Err(err) = game.settings.save() {
+                        eprintln!("Couldn't save settings: {}", err);
                     }
-                },
-            );
+                    game.rebinding = None;
+                    game.state = GameState::Options
+                }
+            });
+            root_ui().pop_skin();
+        }
+        GameState::Info => {
+            let controls = controls_summary(&game.settings.bindings, game.content.weapons.len());
+            root_ui().push_skin(&game.ui_skin);
+            menu_window(hash!(), |ui| {
+                widgets::Label::new("Grillageddon")
+                    .position(scaled(vec2(260.0, 10.0)))
+                    .ui(ui);
+
+                widgets::Label::new(controls)
+                    .position(scaled(vec2(100.0, 100.0)))
+                    .ui(ui);
+
+                widgets::Label::new(
+                    "Enemies drop health, ammo crates and power-up orbs"
+                )
+                .position(scaled(vec2(100.0, 200.0)))
+                .ui(ui);
+
+                widgets::Label::new(
+                    "Power-up timers are shown on the right"
+                )
+                .position(scaled(vec2(100.0, 230.0)))
+                .ui(ui);
+
+                let back_button = widgets::Button::new("Back")
+                    .position(scaled(vec2(300., 400.0)))
+                    .ui(ui);
+
+                if back_button {
+                    audio.play(SoundType::MenuClick);
+                    game.state = GameState::Menu
+                }
+            });
             root_ui().pop_skin();
         }
         GameState::Over => {
             root_ui().push_skin(&game.ui_skin);
-            root_ui().window(
-                hash!(),
-                vec2(0.0 - 5., 0.0),
-                vec2(screen_width() + 5., screen_height() + 5.0),
-                |ui| {
-                    widgets::Label::new("Grillageddon")
-                        .position(vec2(260.0, 10.0))
-                        .ui(ui);
+            menu_window(hash!(), |ui| {
+                widgets::Label::new("Grillageddon")
+                    .position(scaled(vec2(260.0, 10.0)))
+                    .ui(ui);
 
-                    widgets::Label::new(
-                        "You Died!! Score ".to_owned() + &game.final_score.to_string(),
-                    )
-                    .position(vec2(100.0, 150.0))
+                widgets::Label::new(
+                    "You Died!! Score ".to_owned() + &game.final_score.to_string(),
+                )
+                .position(scaled(vec2(100.0, 150.0)))
+                .ui(ui);
+
+                widgets::Label::new("Seed ".to_owned() + &game.final_seed.to_string())
+                    .position(scaled(vec2(100.0, 200.0)))
                     .ui(ui);
 
+                widgets::Label::new("Reached wave ".to_owned() + &game.final_wave.to_string())
+                    .position(scaled(vec2(100.0, 250.0)))
+                    .ui(ui);
+
+                if game.pending_score.is_some() {
+                    widgets::Label::new("New high score! Enter your name")
+                        .position(scaled(vec2(100.0, 300.0)))
+                        .ui(ui);
+
+                    widgets::Editbox::new(hash!(), scaled(vec2(300.0, 40.0)))
+                        .position(scaled(vec2(250.0, 350.0)))
+                        .multiline(false)
+                        .ui(ui, &mut game.name_input);
+                    game.name_input = game.name_input.chars().take(MAX_NAME_LEN).collect();
+
+                    let save_button = widgets::Button::new("Save")
+                        .position(scaled(vec2(300., 420.0)))
+                        .ui(ui);
+
+                    if save_button || game.settings.bindings.pressed(Action::Accept) {
+                        audio.play(SoundType::MenuClick);
+                        save_high_score(game);
+                        game.state = GameState::HighScores
+                    }
+                } else {
                     let back_button = widgets::Button::new("Back")
-                        .position(vec2(300., 300.0))
+                        .position(scaled(vec2(300., 300.0)))
                         .ui(ui);
 
                     if back_button {
                         game.state = GameState::Menu
                     }
-                },
-            );
+                }
+            });
             root_ui().pop_skin();
         },
-    };
-}
-
-fn player_powerups(game: &mut Game) {
-    // Look into switching powerups every 50 points
-    let random: i32;
-    if game.score % 50 == 0 && game.can_get_powerup{
+        GameState::HighScores => {
+            root_ui().push_skin(&game.ui_skin);
+            menu_window(hash!(), |ui| {
+                widgets::Label::new("High Scores")
+                    .position(scaled(vec2(280.0, 10.0)))
+                    .ui(ui);
 
-        random = rand::gen_range(1, 3);
-        
-        if random == 1{
-            game.player.has_power_up = true;
-            game.player.power_up = PowerUpType::FastAttack;
-        }
-        
-        if random == 2 {
-            game.player.has_power_up = true;
-            game.player.power_up = PowerUpType::QuadDamage;
-        }
+                if game.high_scores.entries.is_empty() {
+                    widgets::Label::new("No runs yet, go and set one")
+                        .position(scaled(vec2(100.0, 100.0)))
+                        .ui(ui);
+                }
 
-        game.can_get_powerup = false;
-    }
+                for (rank, entry) in game.high_scores.entries.iter().enumerate() {
+                    let time = entry.time as u32;
+                    let row = format!(
+                        "{}. {}  {}  {}:{:02}  {}  {}  {}",
+                        rank + 1,
+                        entry.name,
+                        entry.score,
+                        time / 60,
+                        time % 60,
+                        entry.weapon,
+                        entry.difficulty.name(),
+                        highscore::format_date(entry.date),
+                    );
+                    widgets::Label::new(row)
+                        .position(scaled(vec2(20.0, 60.0 + rank as f32 * 36.0)))
+                        .ui(ui);
+                }
 
-    
-    if game.player.has_power_up == true {
-        game.power_up_timer += 0.1;
-        
-        if game.power_up_timer >= 50.0 {
-            game.player.power_up = PowerUpType::None;
-            game.player.has_power_up = false;
-            game.power_up_timer = 0.0;
-            game.can_get_powerup = true;
-        }
-    }
-    
+                let back_button = widgets::Button::new("Back")
+                    .position(scaled(vec2(300., 450.0)))
+                    .ui(ui);
 
-    match game.player.power_up {
-        player::PowerUpType::None => {
-            // Reset player back to normal
-            game.player.fire_rate           = 0.1;
-            game.player.shotgun_fire_rate   = 0.9;
-            game.player.damage              = 5 
+                if back_button {
+                    audio.play(SoundType::MenuClick);
+                    game.state = GameState::Menu
+                }
+            });
+            root_ui().pop_skin();
         },
-        player::PowerUpType::FastAttack => {
-            game.player.fire_rate           = 0.05;
-            game.player.shotgun_fire_rate   = 0.05;
+        GameState::Replays => {
+            let mut picked = None;
+            root_ui().push_skin(&game.ui_skin);
+            menu_window(hash!(), |ui| {
+                widgets::Label::new("Replays")
+                    .position(scaled(vec2(300.0, 10.0)))
+                    .ui(ui);
 
+                // Where to drop a replay someone else sent
+                let folder = replay::dir().map_or("no data directory".to_owned(), |dir| dir.display().to_string());
+                widgets::Label::new(format!("From {}", folder))
+                    .position(scaled(vec2(20.0, 50.0)))
+                    .ui(ui);
+
+                if game.replays.is_empty() {
+                    widgets::Label::new("No replays yet, every finished run is saved")
+                        .position(scaled(vec2(100.0, 120.0)))
+                        .ui(ui);
+                } else {
+                    // Scrolls once the list is longer than the group
+                    widgets::Group::new(hash!(), scaled(vec2(600.0, 320.0)))
+                        .position(scaled(vec2(100.0, 100.0)))
+                        .ui(ui, |ui| {
+                            for path in game.replays.iter() {
+                                let name = path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+                                if widgets::Button::new(name).ui(ui) {
+                                    audio.play(SoundType::MenuClick);
+                                    picked = Some(path.clone());
+                                }
+                            }
+                        });
+                }
+
+                let back_button = widgets::Button::new("Back")
+                    .position(scaled(vec2(300., 450.0)))
+                    .ui(ui);
+
+                if back_button {
+                    audio.play(SoundType::MenuClick);
+                    game.state = GameState::Menu
+                }
+            });
+            root_ui().pop_skin();
+
+            if let Some(path) = picked {
+                start_playback(game, &path);
+            }
         },
-        player::PowerUpType::QuadDamage => {
-            game.player.damage = 20; 
-        },
-    }  
-}
-pub struct SpawnPoint {
-    pos: Vec2,
-    texture: Texture2D,
-}
-impl SpawnPoint {
-    pub fn new(pos: Vec2, texture: Texture2D) -> SpawnPoint {
-        SpawnPoint {
-            pos: pos,
-            texture: texture.clone(),
-        }
-    }
+    };
 }
@@ -0,0 +1,94 @@
+use macroquad::prelude::*;
+use serde::Deserialize;
+
+use crate::assets::{self, TextureId};
+use crate::power_up::PowerUpId;
+use crate::weapon::WeaponId;
+
+/// What picking something up does.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Effect {
+    /// Gives back health, up to the player's maximum
+    Health { amount: i32 },
+    /// Switches to a weapon, named from `assets/weapons.toml`, and adds to its reserve
+    Weapon {
+        weapon: String,
+        #[serde(default)]
+        ammo: u32,
+        /// Looked up from `weapon` when the content is loaded
+        #[serde(skip)]
+        id: Option<WeaponId>,
+    },
+    /// Starts a power-up, named from `assets/power_ups.toml`
+    PowerUp {
+        power_up: String,
+        /// Looked up from `power_up` when the content is loaded
+        #[serde(skip)]
+        id: Option<PowerUpId>,
+    },
+}
+
+/// One entry of `assets/pickups.toml`.
+#[derive(Debug, Deserialize)]
+pub struct PickupDef {
+    pub name: String,
+    pub effect: Effect,
+    pub sprite: String,
+    /// Registered from `sprite` when the content is loaded
+    #[serde(skip, default = "default_texture")]
+    pub texture: TextureId,
+    /// Width and height of the sprite
+    pub size: [f32; 2],
+    /// RGBA
+    pub tint: [u8; 4],
+    /// Seconds a dropped pickup lies around before vanishing
+    pub lifetime: f64,
+    /// Seconds before a pickup placed on the map comes back once taken
+    pub respawn: f64,
+    pub sound: String,
+}
+
+impl PickupDef {
+    pub fn tint(&self) -> Color {
+        Color::from_rgba(self.tint[0], self.tint[1], self.tint[2], self.tint[3])
+    }
+}
+
+/// Handle to a kind of pickup, its position in the pickup table.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PickupKind(pub usize);
+
+/// A chance of an enemy leaving a pickup behind when it dies.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Drop {
+    /// Name from `assets/pickups.toml`
+    pub pickup: String,
+    /// Looked up from `pickup` when the content is loaded
+    #[serde(skip)]
+    pub kind: Option<PickupKind>,
+    /// 0 to 1
+    pub chance: f32,
+}
+
+pub struct Pickup {
+    pub kind: PickupKind,
+    /// Middle of the pickup
+    pub position: Vec2,
+    /// When a dropped pickup vanishes. Pickups placed on the map stay until taken
+    pub expires_at: Option<f64>,
+    /// The map spot it was placed at, if it was
+    pub spot: Option<usize>,
+}
+
+/// A place on the map a pickup appears, and keeps coming back to after it's taken.
+pub struct PickupSpot {
+    pub kind: PickupKind,
+    pub position: Vec2,
+    /// When the next one appears, infinity while there's one waiting to be taken
+    pub next_spawn: f64,
+}
+
+fn default_texture() -> TextureId {
+    assets::BULLET
+}
@@ -1,60 +1,70 @@
 use macroquad::prelude::*;
 
-pub enum WeaponType {
-    Pistol,
-    Machine,
-    Shotgun,
-}
+use crate::assets::TextureId;
+use crate::power_up::{ActivePowerUp, Modifiers};
+use crate::weapon::{Ammo, WeaponId};
 
-#[derive(Debug)]
-pub enum PowerUpType {
-    None,
-    FastAttack,
-    QuadDamage,
-}
+pub const PLAYER_SIZE: Vec2 = Vec2::new(17.0, 32.0);
 
 pub struct Player {
     pub position: Vec2,
+    /// Position at the start of the current tick, used to interpolate rendering
+    pub prev_position: Vec2,
+    /// Pixels per second
     pub velocity: Vec2,
+    /// Pixels per second, per second
     pub acceleration: f32,
+    /// Pixels per second, per second
     pub friction: f32,
     pub health: i32,
+    /// Health pickups don't heal past this
+    pub max_health: i32,
+    /// How close in pixels the middle of a pickup has to come to the player's middle to be picked up
+    pub pickup_radius: f32,
+    /// Top speed in pixels per second
     pub speed: f32,
-    pub texture: Texture2D,
+    pub texture: TextureId,
+    pub aim: Vec2,
     pub coll_rect: Rect,
-    pub fram_index: i32,
-    pub frame_time: f32,
-    pub weapon_type: WeaponType,
+    pub weapon: WeaponId,
+    /// When each weapon was unlocked, indexed by weapon, with the ones the player starts with
+    /// unlocked at time zero
+    pub unlocked_at: Vec<Option<f64>>,
+    /// Indexed by weapon
+    pub ammo: Vec<Ammo>,
+    /// When the reload going on finishes
+    pub reloading_until: Option<f64>,
     pub last_shot: f64,
-    pub fire_rate: f64,
-    pub shotgun_fire_rate: f64,
     pub is_dead: bool,
-    pub damage: i32,
-    pub power_up: PowerUpType,
-    pub has_power_up: bool
+    /// Every power-up running, oldest first
+    pub power_ups: Vec<ActivePowerUp>,
+    /// What the running power-ups add up to, worked out at the start of each tick
+    pub modifiers: Modifiers,
 }
 
 impl Player {
-    pub fn new(position: Vec2, speed: f32, texture: Texture2D) -> Player {
+    pub fn new(position: Vec2, speed: f32, texture: TextureId) -> Player {
         Player {
-            position: position,
+            position,
+            prev_position: position,
             velocity: Vec2::new(0.0, 0.0),
-            acceleration: 1.0,
-            friction: 0.5,
+            acceleration: 3600.0,
+            friction: 1800.0,
             health: 500,
-            speed: speed,
-            texture: texture.clone(),
-            coll_rect: Rect::new(position.x, position.y, texture.width(), texture.height()),
-            fram_index: 0,
-            frame_time: 0.0,
-            weapon_type: WeaponType::Pistol,
-            last_shot: get_time(),
-            fire_rate: 0.1,
-            shotgun_fire_rate: 0.9,
+            max_health: 500,
+            pickup_radius: 24.0,
+            speed,
+            texture,
+            aim: position,
+            coll_rect: Rect::new(position.x, position.y, PLAYER_SIZE.x, PLAYER_SIZE.y),
+            weapon: WeaponId(0),
+            unlocked_at: Vec::new(),
+            ammo: Vec::new(),
+            reloading_until: None,
+            last_shot: 0.0,
             is_dead: false,
-            damage: 5,
-            power_up: PowerUpType::None,
-            has_power_up: false
+            power_ups: Vec::new(),
+            modifiers: Modifiers::default(),
         }
     }
 }
@@ -0,0 +1,123 @@
+use macroquad::prelude::*;
+use serde::Deserialize;
+
+use crate::bullet::Projectile;
+
+/// What picking up a power-up again does while it's still running.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stacking {
+    /// Starts its timer over
+    Refresh,
+    /// Adds its duration on to the time left
+    Extend,
+    /// Runs another copy alongside, up to `max_stacks`, each layering its modifiers on top
+    Stack,
+}
+
+/// Changes a power-up makes to the player's stats while it runs.
+///
+/// Missing entries leave the stat alone, so a power-up only has to list what it changes.
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default)]
+pub struct Modifiers {
+    /// Scales the time between shots
+    pub fire_rate: f64,
+    /// Scales the damage of every bullet
+    pub damage: f32,
+    /// Scales top speed and acceleration
+    pub move_speed: f32,
+    /// Fraction of incoming damage blocked, 0 to 1
+    pub shield: f32,
+    /// Extra enemies each bullet passes through
+    pub piercing: u32,
+    /// Extra times each bullet glances off walls
+    pub bounces: u32,
+    /// Degrees a second added to how fast bullets turn towards the nearest enemy
+    pub homing: f32,
+    /// Pixels added to the blast around where each bullet stops
+    pub blast_radius: f32,
+    /// Extra bullets fanned out around every shot
+    pub spread: u32,
+}
+
+impl Default for Modifiers {
+    fn default() -> Modifiers {
+        Modifiers {
+            fire_rate: 1.0,
+            damage: 1.0,
+            move_speed: 1.0,
+            shield: 0.0,
+            piercing: 0,
+            bounces: 0,
+            homing: 0.0,
+            blast_radius: 0.0,
+            spread: 0,
+        }
+    }
+}
+
+impl Modifiers {
+    /// Layers `other` on top. Scales multiply, extras add up, and each shield blocks its share of
+    /// whatever gets through the others, so shields never add up to full immunity.
+    pub fn combine(&mut self, other: &Modifiers) {
+        self.fire_rate *= other.fire_rate;
+        self.damage *= other.damage;
+        self.move_speed *= other.move_speed;
+        self.shield = 1.0 - (1.0 - self.shield) * (1.0 - other.shield);
+        self.piercing += other.piercing;
+        self.bounces += other.bounces;
+        self.homing += other.homing;
+        self.blast_radius += other.blast_radius;
+        self.spread += other.spread;
+    }
+
+    /// A weapon's bullet behaviours with these layered on top.
+    pub fn projectile(&self, base: &Projectile) -> Projectile {
+        Projectile {
+            piercing: base.piercing + self.piercing,
+            bounces: base.bounces + self.bounces,
+            homing: base.homing + self.homing,
+            blast_radius: base.blast_radius + self.blast_radius,
+            ..*base
+        }
+    }
+}
+
+/// One entry of `assets/power_ups.toml`.
+#[derive(Debug, Deserialize)]
+pub struct PowerUpDef {
+    pub name: String,
+    /// Seconds it lasts
+    pub duration: f64,
+    pub stacking: Stacking,
+    /// How many copies can run at once when `stacking` is `stack`
+    #[serde(default = "one")]
+    pub max_stacks: u32,
+    /// RGBA, used for its timer on the HUD
+    pub tint: [u8; 4],
+    #[serde(default)]
+    pub modifiers: Modifiers,
+}
+
+impl PowerUpDef {
+    pub fn tint(&self) -> Color {
+        Color::from_rgba(self.tint[0], self.tint[1], self.tint[2], self.tint[3])
+    }
+}
+
+/// Handle to a power-up, its position in the power-up table.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PowerUpId(pub usize);
+
+/// A power-up the player has running. A stacked power-up has one of these per copy.
+#[derive(Clone, Copy, Debug)]
+pub struct ActivePowerUp {
+    pub id: PowerUpId,
+    pub started_at: f64,
+    pub expires_at: f64,
+}
+
+fn one() -> u32 {
+    1
+}
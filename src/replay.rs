@@ -0,0 +1,318 @@
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use macroquad::prelude::*;
+
+use crate::content::Content;
+use crate::input::Input;
+use crate::settings::Difficulty;
+use crate::world::{Event, World, TIME_STEP};
+
+const EXTENSION: &str = "grpl";
+const MAGIC: &[u8; 4] = b"GRPL";
+const FORMAT_VERSION: u8 = 6;
+
+/// Everything needed to play a run back: the seed it started from and the input fed to every tick.
+///
+/// On disk the inputs are run-length encoded, so long stretches of holding the same keys with
+/// the mouse still cost a handful of bytes.
+pub struct Replay {
+    pub version: String,
+    pub seed: u64,
+    pub difficulty: Difficulty,
+    pub inputs: Vec<Input>,
+    /// `World::checksum` after the last tick, used to spot a replay that no longer plays out the same
+    pub checksum: u64,
+}
+
+impl Replay {
+    pub fn new(seed: u64, difficulty: Difficulty) -> Replay {
+        Replay {
+            version: env!("CARGO_PKG_VERSION").to_owned(),
+            seed,
+            difficulty,
+            inputs: Vec::new(),
+            checksum: 0,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(FORMAT_VERSION);
+        bytes.push(self.version.len() as u8);
+        bytes.extend_from_slice(self.version.as_bytes());
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        bytes.push(self.difficulty as u8);
+        bytes.extend_from_slice(&self.checksum.to_le_bytes());
+        bytes.extend_from_slice(&(self.inputs.len() as u32).to_le_bytes());
+
+        let mut i = 0;
+        while i < self.inputs.len() {
+            let input = self.inputs[i];
+            let mut run: u16 = 1;
+            while run < u16::MAX
+                && i + (run as usize) < self.inputs.len()
+                && self.inputs[i + run as usize] == input
+            {
+                run += 1;
+            }
+
+            bytes.extend_from_slice(&run.to_le_bytes());
+            bytes.push(pack_flags(&input));
+            bytes.push(input.select_weapon.map_or(0, |slot| slot as u8 + 1));
+            bytes.extend_from_slice(&input.aim.x.to_le_bytes());
+            bytes.extend_from_slice(&input.aim.y.to_le_bytes());
+            i += run as usize;
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Replay> {
+        let mut reader = Reader { bytes, pos: 0 };
+        if reader.take(4)? != MAGIC {
+            return Err(invalid("not a replay file"));
+        }
+        if reader.u8()? != FORMAT_VERSION {
+            return Err(invalid("unsupported replay format"));
+        }
+        let version_len = reader.u8()? as usize;
+        let version = String::from_utf8(reader.take(version_len)?.to_vec())
+            .map_err(|_| invalid("bad game version"))?;
+        let seed = reader.u64()?;
+        let difficulty = *Difficulty::ALL
+            .get(reader.u8()? as usize)
+            .ok_or_else(|| invalid("unknown difficulty"))?;
+        let checksum = reader.u64()?;
+        let tick_count = reader.u32()? as usize;
+
+        let mut inputs = Vec::new();
+        while inputs.len() < tick_count {
+            let run = reader.u16()? as usize;
+            let mut input = unpack_flags(reader.u8()?);
+            input.select_weapon = reader.u8()?.checked_sub(1).map(|slot| slot as usize);
+            input.aim = Vec2::new(reader.f32()?, reader.f32()?);
+            if run == 0 || inputs.len() + run > tick_count {
+                return Err(invalid("corrupt input stream"));
+            }
+            inputs.extend(std::iter::repeat_n(input, run));
+        }
+
+        Ok(Replay {
+            version,
+            seed,
+            difficulty,
+            inputs,
+            checksum,
+        })
+    }
+
+    pub fn load(path: &Path) -> io::Result<Replay> {
+        Replay::from_bytes(&fs::read(path)?)
+    }
+
+    /// Writes the replay into the replay folder under a name built from the time and seed.
+    pub fn save(&self) -> io::Result<PathBuf> {
+        let dir = dir().ok_or_else(|| io::Error::new(ErrorKind::NotFound, "no data directory"))?;
+        fs::create_dir_all(&dir)?;
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_secs())
+            .unwrap_or(0);
+        let path = dir.join(format!("{}-{}.{}", timestamp, self.seed, EXTENSION));
+        fs::write(&path, self.to_bytes())?;
+        Ok(path)
+    }
+}
+
+/// Where replays are saved, and where one from someone else can be dropped to watch it.
+pub fn dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("grillageddon").join("replays"))
+}
+
+/// Every replay in the replay folder, newest first.
+pub fn list() -> Vec<PathBuf> {
+    let Some(entries) = dir().and_then(|dir| fs::read_dir(dir).ok()) else {
+        return Vec::new();
+    };
+    let mut replays: Vec<_> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == EXTENSION))
+        .map(|entry| {
+            (
+                entry.metadata().and_then(|meta| meta.modified()).ok(),
+                entry.path(),
+            )
+        })
+        .collect();
+    replays.sort_by(|a, b| b.cmp(a));
+    replays.into_iter().map(|(_, path)| path).collect()
+}
+
+fn pack_flags(input: &Input) -> u8 {
+    (input.up as u8)
+        | (input.down as u8) << 1
+        | (input.left as u8) << 2
+        | (input.right as u8) << 3
+        | (input.fire_pressed as u8) << 4
+        | (input.fire_down as u8) << 5
+        | (input.reload_pressed as u8) << 6
+}
+
+fn unpack_flags(flags: u8) -> Input {
+    Input {
+        up: flags & 1 != 0,
+        down: flags & 1 << 1 != 0,
+        left: flags & 1 << 2 != 0,
+        right: flags & 1 << 3 != 0,
+        fire_pressed: flags & 1 << 4 != 0,
+        fire_down: flags & 1 << 5 != 0,
+        reload_pressed: flags & 1 << 6 != 0,
+        select_weapon: None,
+        aim: Vec2::ZERO,
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message)
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        let slice = self
+            .bytes
+            .get(self.pos..self.pos + len)
+            .ok_or_else(|| invalid("replay file is truncated"))?;
+        self.pos += len;
+        Ok(slice)
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> io::Result<u16> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn f32(&mut self) -> io::Result<f32> {
+        Ok(f32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+}
+
+/// A replay being watched, with its own [`World`] driven by the recorded inputs.
+pub struct Playback {
+    pub replay: Replay,
+    pub world: World,
+    pub tick: usize,
+    pub paused: bool,
+    pub speed: u32,
+    pub accumulator: f32,
+}
+
+impl Playback {
+    pub fn new(replay: Replay, content: Rc<Content>) -> Playback {
+        let world = World::new(replay.seed, replay.difficulty, content);
+        Playback {
+            replay,
+            world,
+            tick: 0,
+            paused: false,
+            speed: 1,
+            accumulator: 0.0,
+        }
+    }
+
+    /// Runs the next recorded tick.
+    pub fn step(&mut self) -> Vec<Event> {
+        let events = self.world.tick(&self.replay.inputs[self.tick], TIME_STEP);
+        self.tick += 1;
+        events
+    }
+
+    pub fn finished(&self) -> bool {
+        self.tick >= self.replay.inputs.len()
+    }
+
+    /// True once playback has ended somewhere other than where the recording did.
+    pub fn desynced(&self) -> bool {
+        self.finished() && self.world.checksum() != self.replay.checksum
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A replay with one input held for `held` ticks, followed by a few that all differ.
+    fn replay(held: usize) -> Replay {
+        let mut replay = Replay::new(99, Difficulty::Hard);
+        let input = Input {
+            up: true,
+            fire_down: true,
+            aim: vec2(12.5, -3.0),
+            ..Default::default()
+        };
+        replay.inputs.extend(std::iter::repeat_n(input, held));
+        for tick in 0..5 {
+            replay.inputs.push(Input {
+                left: tick % 2 == 0,
+                reload_pressed: tick == 3,
+                select_weapon: Some(tick),
+                aim: vec2(tick as f32, 0.5),
+                ..Default::default()
+            });
+        }
+        replay.checksum = 0xdead_beef;
+        replay
+    }
+
+    #[test]
+    fn round_trips_through_bytes() {
+        // Longer than one run length can hold, so the held input is split over several runs
+        let replay = replay(u16::MAX as usize * 2 + 10);
+        let loaded = Replay::from_bytes(&replay.to_bytes()).unwrap();
+        assert_eq!(loaded.version, replay.version);
+        assert_eq!(loaded.seed, replay.seed);
+        assert_eq!(loaded.difficulty, replay.difficulty);
+        assert_eq!(loaded.checksum, replay.checksum);
+        assert_eq!(loaded.inputs, replay.inputs);
+    }
+
+    #[test]
+    fn bad_files_are_errors() {
+        let bytes = Replay::new(1, Difficulty::Normal).to_bytes();
+        let mut bad_magic = bytes.clone();
+        bad_magic[0] = b'X';
+        assert!(Replay::from_bytes(&bad_magic).is_err());
+
+        let bytes = replay(1).to_bytes();
+        for len in 0..bytes.len() {
+            assert!(Replay::from_bytes(&bytes[..len]).is_err());
+        }
+
+        // The last run starts with its length, ahead of the flags, weapon slot and aim
+        let mut empty_run = replay(0).to_bytes();
+        let run = empty_run.len() - 12;
+        empty_run[run..run + 2].copy_from_slice(&0u16.to_le_bytes());
+        assert!(Replay::from_bytes(&empty_run).is_err());
+    }
+}
@@ -0,0 +1,129 @@
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    path::PathBuf,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::input::Bindings;
+
+/// Window sizes offered on the options screen.
+pub const RESOLUTIONS: [[u32; 2]; 4] = [[800, 600], [1024, 768], [1280, 960], [1600, 1200]];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    /// In the order the options screen lists them, which is also how replays store them.
+    pub const ALL: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Normal => "Normal",
+            Difficulty::Hard => "Hard",
+        }
+    }
+
+    /// Multiplies every enemy's health
+    pub fn enemy_health(self) -> f32 {
+        match self {
+            Difficulty::Easy => 0.75,
+            Difficulty::Normal => 1.0,
+            Difficulty::Hard => 1.5,
+        }
+    }
+
+    /// Multiplies all the damage enemies do, by touch and by their bullets, bosses included
+    pub fn enemy_damage(self) -> f32 {
+        match self {
+            Difficulty::Easy => 0.5,
+            Difficulty::Normal => 1.0,
+            Difficulty::Hard => 1.5,
+        }
+    }
+
+    /// Multiplies the time between spawns within a wave
+    pub fn spawn_interval(self) -> f64 {
+        match self {
+            Difficulty::Easy => 1.25,
+            Difficulty::Normal => 1.0,
+            Difficulty::Hard => 0.8,
+        }
+    }
+}
+
+/// Everything the player can change on the options screen.
+///
+/// Lives in `settings.toml` in the user's config directory. Missing keys fall back to their
+/// defaults, so the file keeps working as settings are added.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// 0 to 1, scales the music and sound effect volumes
+    pub master_volume: f32,
+    /// 0 to 1. Nothing plays music yet, but the setting is kept for when something does
+    pub music_volume: f32,
+    /// 0 to 1
+    pub sfx_volume: f32,
+    pub fullscreen: bool,
+    /// Window width and height when not fullscreen
+    pub resolution: [u32; 2],
+    pub screen_shake: bool,
+    pub difficulty: Difficulty,
+    pub bindings: Bindings,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings {
+            master_volume: 1.0,
+            music_volume: 1.0,
+            sfx_volume: 1.0,
+            fullscreen: false,
+            resolution: RESOLUTIONS[0],
+            screen_shake: true,
+            difficulty: Difficulty::Normal,
+            bindings: Bindings::default(),
+        }
+    }
+}
+
+impl Settings {
+    /// Reads the settings file, falling back to the defaults if there isn't one or it can't be read.
+    pub fn load() -> Settings {
+        let Some(path) = settings_path() else {
+            return Settings::default();
+        };
+
+        match fs::read_to_string(&path) {
+            Ok(text) => toml::from_str(&text).unwrap_or_else(|err| {
+                eprintln!("Ignoring broken settings in {}: {}", path.display(), err);
+                Settings::default()
+            }),
+            Err(err) if err.kind() == ErrorKind::NotFound => Settings::default(),
+            Err(err) => {
+                eprintln!("Couldn't read {}: {}", path.display(), err);
+                Settings::default()
+            }
+        }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = settings_path()
+            .ok_or_else(|| io::Error::new(ErrorKind::NotFound, "no config directory"))?;
+        let text = toml::to_string(self).map_err(io::Error::other)?;
+        fs::create_dir_all(path.parent().unwrap())?;
+        fs::write(path, text)
+    }
+}
+
+fn settings_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("grillageddon").join("settings.toml"))
+}
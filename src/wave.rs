@@ -0,0 +1,115 @@
+use libm::{pow, powf};
+use serde::Deserialize;
+
+use crate::enemy::EnemyKind;
+
+/// `assets/waves.toml`: the hand made waves, plus how to keep going once they run out.
+#[derive(Debug, Deserialize)]
+pub struct WaveTable {
+    /// Seconds of quiet before each wave, including the first
+    pub intermission: f64,
+    pub endless: Endless,
+    #[serde(rename = "wave")]
+    pub waves: Vec<WaveDef>,
+}
+
+/// How the last wave in the table is stretched for every wave after it.
+#[derive(Debug, Deserialize)]
+pub struct Endless {
+    /// Every group's count is multiplied by this once per extra wave
+    pub count_scale: f32,
+    /// The spawn interval is multiplied by this once per extra wave
+    pub interval_scale: f64,
+    /// The spawn interval never drops below this
+    pub min_interval: f64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WaveDef {
+    /// Seconds between spawns
+    pub spawn_interval: f64,
+    pub groups: Vec<WaveGroup>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WaveGroup {
+    /// Name from `assets/enemies.toml`, or left out for a random pick by `spawn_weight`
+    pub enemy: Option<String>,
+    /// Looked up from `enemy` when the content is loaded
+    #[serde(skip)]
+    pub kind: Option<EnemyKind>,
+    pub count: u32,
+}
+
+impl WaveTable {
+    /// The spawn interval for wave `number`, counting from 1.
+    pub fn spawn_interval(&self, number: u32) -> f64 {
+        let (wave, extra) = self.wave(number);
+        (wave.spawn_interval * pow(self.endless.interval_scale, extra as f64))
+            .max(self.endless.min_interval)
+            .min(wave.spawn_interval)
+    }
+
+    /// Everything wave `number` sends, in no particular order. `None` is a random pick.
+    pub fn spawns(&self, number: u32) -> Vec<Option<EnemyKind>> {
+        let (wave, extra) = self.wave(number);
+        let scale = powf(self.endless.count_scale, extra as f32);
+
+        let mut spawns = Vec::new();
+        for group in wave.groups.iter() {
+            let count = (group.count as f32 * scale).round() as usize;
+            spawns.extend(std::iter::repeat_n(group.kind, count));
+        }
+        spawns
+    }
+
+    /// The definition wave `number` is built from and how many waves past the end of the table it is.
+    fn wave(&self, number: u32) -> (&WaveDef, u32) {
+        let index = (number as usize).saturating_sub(1);
+        match self.waves.get(index) {
+            Some(wave) => (wave, 0),
+            None => (
+                self.waves.last().unwrap(),
+                (index + 1 - self.waves.len()) as u32,
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WavePhase {
+    /// Waiting for the next wave, which starts at the given time
+    Intermission { until: f64 },
+    /// Still sending out the wave
+    Spawning,
+    /// Everything has spawned, waiting for the player to finish them off
+    Clearing,
+}
+
+/// Where the run is in the wave table.
+pub struct WaveDirector {
+    /// The wave being fought, or the one about to start during an intermission. Counts from 1.
+    pub number: u32,
+    pub phase: WavePhase,
+    /// Enemies still to spawn this wave, taken from the back
+    pub queue: Vec<Option<EnemyKind>>,
+    pub spawn_interval: f64,
+    pub last_spawn: f64,
+    /// When the current wave started, for the announcement
+    pub started_at: f64,
+}
+
+impl WaveDirector {
+    pub fn new(intermission: f64) -> WaveDirector {
+        WaveDirector {
+            number: 1,
+            phase: WavePhase::Intermission {
+                until: intermission,
+            },
+            queue: Vec::new(),
+            spawn_interval: 0.0,
+            last_spawn: 0.0,
+            started_at: 0.0,
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use serde::Deserialize;
+
+use crate::bullet::Projectile;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FireMode {
+    /// One shot per click
+    Semi,
+    /// Keeps firing while the button is held
+    Auto,
+}
+
+/// When a weapon the player doesn't start with becomes theirs. Picking up a crate for it always
+/// unlocks it, whatever this says.
+#[derive(Debug, Default, Deserialize)]
+pub struct Unlock {
+    /// Unlocks as this wave starts
+    pub at_wave: Option<u32>,
+    /// Unlocks once the score reaches this
+    pub at_score: Option<i32>,
+}
+
+/// One entry of `assets/weapons.toml`.
+#[derive(Debug, Deserialize)]
+pub struct WeaponDef {
+    pub name: String,
+    pub icon: String,
+    pub fire_mode: FireMode,
+    /// Seconds between shots
+    pub fire_rate: f64,
+    /// Per pellet
+    pub damage: i32,
+    /// Pixels per second
+    pub projectile_speed: f32,
+    /// Pixels a bullet flies before fizzling out
+    pub range: f32,
+    #[serde(default = "one")]
+    pub pellets: u32,
+    /// Degrees between the outermost pellets
+    #[serde(default)]
+    pub spread: f32,
+    #[serde(default)]
+    pub projectile: Projectile,
+    /// Shots before it needs reloading, or `None` if it never does
+    pub magazine: Option<u32>,
+    /// Shots carried for it on top of the loaded ones at the start of a run, or `None` for an
+    /// endless supply
+    pub reserve: Option<u32>,
+    /// Seconds a reload takes
+    #[serde(default)]
+    pub reload_time: f64,
+    /// Leave out for a weapon the player starts with
+    pub unlock: Option<Unlock>,
+    pub sound: String,
+    /// Played on pulling the trigger with nothing left to fire
+    pub dry_fire_sound: String,
+    pub reload_sound: String,
+}
+
+/// Handle to a weapon, its position in the weapon table.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WeaponId(pub usize);
+
+/// The shots the player has for one weapon.
+#[derive(Clone, Copy, Debug)]
+pub struct Ammo {
+    /// In the magazine, ready to fire
+    pub loaded: u32,
+    /// Carried on top, `None` for an endless supply
+    pub reserve: Option<u32>,
+}
+
+impl Ammo {
+    /// A full magazine and the weapon's starting reserve.
+    pub fn new(def: &WeaponDef) -> Ammo {
+        Ammo {
+            loaded: def.magazine.unwrap_or(0),
+            reserve: def.reserve,
+        }
+    }
+
+    /// Whether there is anything left to reload with.
+    pub fn has_reserve(&self) -> bool {
+        self.reserve.is_none_or(|reserve| reserve > 0)
+    }
+}
+
+fn one() -> u32 {
+    1
+}
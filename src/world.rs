@@ -0,0 +1,1244 @@
+use std::{
+    f32::consts::{PI, TAU},
+    rc::Rc,
+};
+
+use ::rand::{seq::SliceRandom, Rng, SeedableRng};
+use libm::{atan2f, cosf, sinf};
+use macroquad::prelude::*;
+use rand_chacha::ChaCha8Rng;
+
+use crate::assets::{self, TextureId};
+use crate::boss::{BossId, Encounter};
+use crate::bullet::{Blast, Bullet, Faction};
+use crate::content::Content;
+use crate::enemy::{Behaviour, Enemy, EnemyKind, FirePattern, RangedAttack};
+use crate::flow::FlowField;
+use crate::grid::SpatialGrid;
+use crate::input::Input;
+use crate::pickup::{Effect, Pickup, PickupKind, PickupSpot};
+use crate::player::{Player, PLAYER_SIZE};
+use crate::power_up::{ActivePowerUp, Modifiers, PowerUpId, Stacking};
+use crate::settings::Difficulty;
+use crate::wave::{WaveDirector, WavePhase};
+use crate::weapon::{Ammo, FireMode, WeaponId};
+
+const MAX_ENEMIES: usize = 1000;
+/// Roughly one enemy wide, so most lookups only have to check a cell or two
+const GRID_CELL_SIZE: f32 = 32.0;
+/// How many overlapping neighbours an enemy steers away from. Once the horde has piled up on
+/// the player every enemy overlaps dozens of others, and a handful is plenty to spread them out.
+const MAX_NEIGHBOURS: usize = 8;
+/// Pixels away a homing bullet can pick out an enemy to turn towards
+const HOMING_RANGE: f32 = 300.0;
+/// Seconds a blast stays around to be drawn
+pub const BLAST_TIME: f64 = 0.3;
+/// Degrees the fan of a shot widens by for every extra bullet a spread power-up adds
+const SPREAD_SHOT_ANGLE: f32 = 10.0;
+const SPAWN_POINT_SIZE: Vec2 = Vec2::new(32.0, 32.0);
+/// Seconds a boss holds still and holds fire after arriving, while it's announced
+pub const BOSS_INTRO_TIME: f64 = 2.0;
+
+/// Length of one simulation tick in seconds. The game always advances in steps of this size
+/// regardless of the display's refresh rate.
+pub const TIME_STEP: f32 = 1.0 / 60.0;
+
+/// Something that happened during a tick that the presentation layer may want to react to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Event {
+    Shot { weapon: WeaponId },
+    DryFire { weapon: WeaponId },
+    ReloadStarted { weapon: WeaponId },
+    WeaponUnlocked { weapon: WeaponId },
+    EnemyHit,
+    EnemyKilled,
+    PlayerHit,
+    PlayerDied { score: i32 },
+    WaveStarted { number: u32 },
+    WaveCleared { number: u32 },
+    BossArrived { boss: BossId },
+    BossPhase { boss: BossId, phase: usize },
+    BossDefeated { boss: BossId },
+    PickedUp { pickup: PickupKind },
+    Exploded,
+}
+
+pub struct SpawnPoint {
+    /// Top left of the marker, which enemies appear in the middle of
+    pub pos: Vec2,
+    pub texture: TextureId,
+}
+
+impl SpawnPoint {
+    pub fn new(pos: Vec2, texture: TextureId) -> SpawnPoint {
+        SpawnPoint { pos, texture }
+    }
+}
+
+/// The game rules, free of any window, input device or clock.
+///
+/// The macroquad layer captures an [`Input`] each frame, hands it to [`World::tick`]
+/// and draws whatever state comes out the other side. All randomness comes from `rng`, so the
+/// same seed fed the same inputs always ends up in exactly the same state. `rng` is a named
+/// algorithm rather than `StdRng`, which may change between rand releases, and the trig goes
+/// through libm rather than the platform's maths library, so this holds across builds too.
+pub struct World {
+    pub seed: u64,
+    pub difficulty: Difficulty,
+    pub rng: ChaCha8Rng,
+    pub content: Rc<Content>,
+    /// The level's size in pixels
+    pub size: Vec2,
+    pub player: Player,
+    pub enemies: Vec<Enemy>,
+    pub bullets: Vec<Bullet>,
+    pub spawn_points: Vec<SpawnPoint>,
+    /// Enemy indices bucketed by position, rebuilt whenever enemies move
+    pub enemy_grid: SpatialGrid,
+    /// The way to the player from everywhere in the level
+    pub flow: FlowField,
+    pub time: f64,
+    pub waves: WaveDirector,
+    /// Which bosses have turned up this run, indexed by boss
+    pub bosses_met: Vec<bool>,
+    pub encounter: Option<Encounter>,
+    pub score: i32,
+    /// How many times each weapon has fired, indexed by weapon
+    pub shots: Vec<u32>,
+    /// Blasts from the last `BLAST_TIME` seconds
+    pub blasts: Vec<Blast>,
+    /// Id the next enemy to spawn gets
+    pub next_enemy_id: u32,
+    pub pickups: Vec<Pickup>,
+    pub pickup_spots: Vec<PickupSpot>,
+}
+
+impl World {
+    pub fn new(seed: u64, difficulty: Difficulty, content: Rc<Content>) -> World {
+        let level = &content.level;
+        let size = level.size;
+        let mut player = Player::new(
+            level.player_start - PLAYER_SIZE / 2.0,
+            180.0,
+            assets::PLAYER,
+        );
+        player.ammo = content.weapons.iter().map(Ammo::new).collect();
+        player.unlocked_at = content
+            .weapons
+            .iter()
+            .map(|def| def.unlock.is_none().then_some(0.0))
+            .collect();
+
+        let spawn_points = level
+            .spawn_points
+            .iter()
+            .map(|&point| SpawnPoint::new(point - SPAWN_POINT_SIZE / 2.0, assets::SPAWN_POINT))
+            .collect();
+
+        let flow = FlowField::new(level);
+        let waves = WaveDirector::new(content.waves.intermission);
+        let shots = vec![0; content.weapons.len()];
+        let bosses_met = vec![false; content.bosses.len()];
+        let pickup_spots = level
+            .pickups
+            .iter()
+            .map(|spot| PickupSpot {
+                kind: spot.kind.unwrap(),
+                position: spot.position,
+                next_spawn: 0.0,
+            })
+            .collect();
+
+        World {
+            seed,
+            difficulty,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            content,
+            size,
+            player,
+            enemies: Vec::new(),
+            bullets: Vec::new(),
+            spawn_points,
+            enemy_grid: SpatialGrid::new(size, GRID_CELL_SIZE),
+            flow,
+            time: 0.0,
+            waves,
+            bosses_met,
+            encounter: None,
+            score: 0,
+            shots,
+            blasts: Vec::new(),
+            next_enemy_id: 0,
+            pickups: Vec::new(),
+            pickup_spots,
+        }
+    }
+
+    /// Advances the simulation by `dt` seconds and returns the events it produced.
+    pub fn tick(&mut self, input: &Input, dt: f32) -> Vec<Event> {
+        let mut events = Vec::new();
+        if self.player.is_dead {
+            return events;
+        }
+
+        self.time += dt as f64;
+        let time = self.time;
+        self.blasts.retain(|blast| time - blast.at < BLAST_TIME);
+        self.player.prev_position = self.player.position;
+        for enemy in self.enemies.iter_mut() {
+            enemy.prev_position = enemy.position;
+        }
+        for bullet in self.bullets.iter_mut() {
+            bullet.prev_position = bullet.position;
+        }
+
+        spawn_enemies(self, &mut events);
+        spawn_bosses(self, &mut events);
+        weapon_unlocks(self, &mut events);
+        player_update(self, input, dt, &mut events);
+        bullet_update(self, input, dt, &mut events);
+        enemy_update(self, dt, &mut events);
+        enemy_attacks(self);
+        collision_check(self, &mut events);
+        boss_update(self, &mut events);
+        pickup_update(self, &mut events);
+        events
+    }
+
+    /// A fingerprint of the simulation state, used to check that a replay ended up where the
+    /// original run did.
+    pub fn checksum(&self) -> u64 {
+        // FNV-1a, which unlike the std hasher is guaranteed to stay the same between releases
+        let mut hash: u64 = 0xcbf29ce484222325;
+        let mut write = |value: u64| {
+            for byte in value.to_le_bytes() {
+                hash ^= byte as u64;
+                hash = hash.wrapping_mul(0x100000001b3);
+            }
+        };
+
+        let word_pos = self.rng.get_word_pos();
+        write(word_pos as u64);
+        write((word_pos >> 64) as u64);
+        write(self.time.to_bits());
+        write(self.score as u64);
+        write(self.waves.number as u64);
+        write(self.player.health as u64);
+        write(self.player.position.x.to_bits() as u64);
+        write(self.player.position.y.to_bits() as u64);
+        write(self.player.velocity.x.to_bits() as u64);
+        write(self.player.velocity.y.to_bits() as u64);
+        for enemy in self.enemies.iter() {
+            write(enemy.health as u64);
+            write(enemy.position.x.to_bits() as u64);
+            write(enemy.position.y.to_bits() as u64);
+        }
+        for bullet in self.bullets.iter() {
+            write(bullet.position.x.to_bits() as u64);
+            write(bullet.position.y.to_bits() as u64);
+        }
+        for pickup in self.pickups.iter() {
+            write(pickup.kind.0 as u64);
+            write(pickup.position.x.to_bits() as u64);
+            write(pickup.position.y.to_bits() as u64);
+        }
+        for active in self.player.power_ups.iter() {
+            write(active.id.0 as u64);
+            write(active.expires_at.to_bits());
+        }
+        hash
+    }
+}
+
+fn spawn_enemies(world: &mut World, events: &mut Vec<Event>) {
+    match world.waves.phase {
+        WavePhase::Intermission { until } => {
+            if world.time >= until {
+                start_wave(world, events);
+            }
+        }
+        WavePhase::Spawning => {
+            if world.time - world.waves.last_spawn > world.waves.spawn_interval
+                && world.enemies.len() < MAX_ENEMIES
+            {
+                let spawn_point =
+                    &world.spawn_points[world.rng.gen_range(0..world.spawn_points.len())];
+                let spawn_centre = spawn_point.pos + SPAWN_POINT_SIZE / 2.0;
+                let kind = world
+                    .waves
+                    .queue
+                    .pop()
+                    .flatten()
+                    .unwrap_or_else(|| pick_enemy_kind(world));
+                let def = &world.content.enemies[kind.0];
+                let enemy_pos = spawn_centre - Vec2::from(def.size) / 2.0;
+                let mut enemy = Enemy::new(enemy_pos, kind, def);
+                enemy.health = (def.health as f32 * world.difficulty.enemy_health()).ceil() as i32;
+                // A full reload before the first volley, rather than shooting on arrival
+                enemy.last_shot = world.time;
+                enemy.spawned_at = world.time;
+                enemy.id = world.next_enemy_id;
+                world.next_enemy_id += 1;
+                world.enemies.push(enemy);
+                world.waves.last_spawn = world.time;
+
+                if world.waves.queue.is_empty() {
+                    world.waves.phase = WavePhase::Clearing;
+                }
+            }
+        }
+        WavePhase::Clearing => {
+            if world.enemies.is_empty() {
+                events.push(Event::WaveCleared {
+                    number: world.waves.number,
+                });
+                world.waves.number += 1;
+                world.waves.phase = WavePhase::Intermission {
+                    until: world.time + world.content.waves.intermission,
+                };
+            }
+        }
+    }
+}
+
+fn start_wave(world: &mut World, events: &mut Vec<Event>) {
+    let number = world.waves.number;
+    let mut queue = world.content.waves.spawns(number);
+    queue.shuffle(&mut world.rng);
+
+    world.waves.phase = if queue.is_empty() {
+        WavePhase::Clearing
+    } else {
+        WavePhase::Spawning
+    };
+    world.waves.queue = queue;
+    world.waves.spawn_interval =
+        world.content.waves.spawn_interval(number) * world.difficulty.spawn_interval();
+    world.waves.started_at = world.time;
+    events.push(Event::WaveStarted { number });
+}
+
+/// Whether wave number `wave` has started, or been and gone.
+fn wave_reached(waves: &WaveDirector, wave: u32) -> bool {
+    let started = !matches!(waves.phase, WavePhase::Intermission { .. });
+    waves.number > wave || (waves.number == wave && started)
+}
+
+/// Unlocks every weapon whose wave has started or whose score has been reached.
+fn weapon_unlocks(world: &mut World, events: &mut Vec<Event>) {
+    for (index, def) in world.content.weapons.iter().enumerate() {
+        let Some(unlock) = &def.unlock else {
+            continue;
+        };
+        let due_by_wave = unlock
+            .at_wave
+            .is_some_and(|wave| wave_reached(&world.waves, wave));
+        let due_by_score = unlock.at_score.is_some_and(|score| world.score >= score);
+        if due_by_wave || due_by_score {
+            unlock_weapon(&mut world.player, WeaponId(index), world.time, events);
+        }
+    }
+}
+
+/// Makes `weapon` selectable, if it wasn't already.
+fn unlock_weapon(player: &mut Player, weapon: WeaponId, time: f64, events: &mut Vec<Event>) {
+    if player.unlocked_at[weapon.0].is_none() {
+        player.unlocked_at[weapon.0] = Some(time);
+        events.push(Event::WeaponUnlocked { weapon });
+    }
+}
+
+/// Brings on every boss whose wave has started or whose score has been reached, once a run.
+fn spawn_bosses(world: &mut World, events: &mut Vec<Event>) {
+    for (index, def) in world.content.bosses.iter().enumerate() {
+        let due_by_wave = def
+            .at_wave
+            .is_some_and(|wave| wave_reached(&world.waves, wave));
+        let due_by_score = def.at_score.is_some_and(|score| world.score >= score);
+        if world.bosses_met[index] || !(due_by_wave || due_by_score) {
+            continue;
+        }
+        world.bosses_met[index] = true;
+
+        // As far from the player as it can, so they have a moment to get ready
+        let player_pos = world.player.coll_rect.center();
+        let spawn_centre = world
+            .spawn_points
+            .iter()
+            .map(|point| point.pos + SPAWN_POINT_SIZE / 2.0)
+            .max_by(|a, b| {
+                a.distance_squared(player_pos)
+                    .total_cmp(&b.distance_squared(player_pos))
+            })
+            .unwrap();
+
+        let boss = BossId(index);
+        let kind = def.kind.unwrap();
+        let enemy_def = &world.content.enemies[kind.0];
+        let mut enemy = Enemy::new(
+            spawn_centre - Vec2::from(enemy_def.size) / 2.0,
+            kind,
+            enemy_def,
+        );
+        enemy.health = (def.health as f32 * world.difficulty.enemy_health()).ceil() as i32;
+        enemy.max_health = enemy.health;
+        enemy.speed = 0.0;
+        enemy.last_shot = world.time;
+        enemy.spawned_at = world.time;
+        enemy.boss = Some(boss);
+        enemy.id = world.next_enemy_id;
+        world.next_enemy_id += 1;
+        world.enemies.push(enemy);
+
+        world.encounter = Some(Encounter {
+            boss,
+            arrived_at: world.time,
+            defeated_at: None,
+        });
+        events.push(Event::BossArrived { boss });
+    }
+}
+
+/// Holds bosses still through their intro, then moves them through their phases as they lose health.
+fn boss_update(world: &mut World, events: &mut Vec<Event>) {
+    for enemy in world.enemies.iter_mut() {
+        let Some(boss) = enemy.boss else {
+            continue;
+        };
+        if world.time - enemy.spawned_at < BOSS_INTRO_TIME {
+            enemy.last_shot = world.time;
+            continue;
+        }
+        if enemy.health <= 0 {
+            continue;
+        }
+
+        let phases = &world.content.bosses[boss.0].phases;
+        let fraction = enemy.health as f32 / enemy.max_health as f32;
+        // Phases only ever move on, even if something were to heal the boss
+        let phase = phases
+            .iter()
+            .rposition(|phase| fraction <= phase.below)
+            .unwrap_or(0)
+            .max(enemy.phase);
+        if phase != enemy.phase {
+            enemy.phase = phase;
+            events.push(Event::BossPhase { boss, phase });
+        }
+        enemy.speed = phases[phase].speed;
+    }
+}
+
+/// How `enemy` moves and shoots. Bosses change both from phase to phase.
+fn tactics<'a>(content: &'a Content, enemy: &Enemy) -> (Behaviour, Option<&'a RangedAttack>) {
+    match enemy.boss {
+        Some(boss) => {
+            let phase = &content.bosses[boss.0].phases[enemy.phase];
+            (phase.behaviour, phase.ranged.as_ref())
+        }
+        None => {
+            let def = &content.enemies[enemy.kind.0];
+            (def.behaviour, def.ranged.as_ref())
+        }
+    }
+}
+
+/// Picks an archetype at random, weighted by each one's `spawn_weight`.
+fn pick_enemy_kind(world: &mut World) -> EnemyKind {
+    let total: u32 = world
+        .content
+        .enemies
+        .iter()
+        .map(|def| def.spawn_weight)
+        .sum();
+    let mut roll = world.rng.gen_range(0..total);
+    for (index, def) in world.content.enemies.iter().enumerate() {
+        if roll < def.spawn_weight {
+            return EnemyKind(index);
+        }
+        roll -= def.spawn_weight;
+    }
+    unreachable!("roll is always below the total weight")
+}
+
+fn player_update(world: &mut World, input: &Input, dt: f32, events: &mut Vec<Event>) {
+    if let Some(slot) = input.select_weapon {
+        // Locked slots stay shut
+        if world
+            .player
+            .unlocked_at
+            .get(slot)
+            .is_some_and(|at| at.is_some())
+        {
+            switch_weapon(&mut world.player, WeaponId(slot));
+        }
+    }
+
+    player_powerups(world);
+    let speed = world.player.speed * world.player.modifiers.move_speed;
+    let acceleration = world.player.acceleration * world.player.modifiers.move_speed * dt;
+    let friction = world.player.friction * dt;
+
+    if input.left {
+        world.player.velocity.x -= acceleration;
+    }
+
+    if input.right {
+        world.player.velocity.x += acceleration;
+    }
+
+    if input.up {
+        world.player.velocity.y -= acceleration;
+    }
+
+    if input.down {
+        world.player.velocity.y += acceleration;
+    }
+
+    world.player.velocity.y = clamp(world.player.velocity.y, -speed, speed);
+    world.player.velocity.x = clamp(world.player.velocity.x, -speed, speed);
+
+    // Friction never pushes the player backwards, it only bleeds speed off towards zero
+    if world.player.velocity.x > 0.0 {
+        world.player.velocity.x = (world.player.velocity.x - friction).max(0.0);
+    } else if world.player.velocity.x < 0.0 {
+        world.player.velocity.x = (world.player.velocity.x + friction).min(0.0);
+    }
+
+    if world.player.velocity.y > 0.0 {
+        world.player.velocity.y = (world.player.velocity.y - friction).max(0.0);
+    } else if world.player.velocity.y < 0.0 {
+        world.player.velocity.y = (world.player.velocity.y + friction).min(0.0);
+    }
+
+    if world.player.health <= 0 {
+        world.player.is_dead = true;
+        events.push(Event::PlayerDied { score: world.score });
+    }
+
+    world.player.aim = input.aim;
+    let delta = world.player.velocity * dt;
+    let moved = world
+        .content
+        .level
+        .slide(world.player.position, PLAYER_SIZE, delta);
+    // Stop dead against walls rather than building up speed pushing into them
+    if moved.x != world.player.position.x + delta.x {
+        world.player.velocity.x = 0.0;
+    }
+    if moved.y != world.player.position.y + delta.y {
+        world.player.velocity.y = 0.0;
+    }
+    world.player.position = moved;
+    world.player.coll_rect.x = world.player.position.x;
+    world.player.coll_rect.y = world.player.position.y;
+}
+
+/// Puts `weapon` in the player's hands, abandoning any reload of the last one.
+fn switch_weapon(player: &mut Player, weapon: WeaponId) {
+    if player.weapon != weapon {
+        player.weapon = weapon;
+        player.reloading_until = None;
+    }
+}
+
+/// Finishes a reload once its time is up, and starts one when asked to or when the magazine is
+/// empty, as long as there is something to reload with.
+fn player_reload(world: &mut World, input: &Input, events: &mut Vec<Event>) {
+    let weapon = world.player.weapon;
+    let def = &world.content.weapons[weapon.0];
+    let Some(magazine) = def.magazine else {
+        return;
+    };
+    let ammo = &mut world.player.ammo[weapon.0];
+
+    match world.player.reloading_until {
+        Some(until) if world.time >= until => {
+            let wanted = magazine - ammo.loaded;
+            let taken = ammo.reserve.map_or(wanted, |reserve| reserve.min(wanted));
+            ammo.loaded += taken;
+            ammo.reserve = ammo.reserve.map(|reserve| reserve - taken);
+            world.player.reloading_until = None;
+        }
+        Some(_) => {}
+        None => {
+            if (input.reload_pressed || ammo.loaded == 0)
+                && ammo.loaded < magazine
+                && ammo.has_reserve()
+            {
+                world.player.reloading_until = Some(world.time + def.reload_time);
+                events.push(Event::ReloadStarted { weapon });
+            }
+        }
+    }
+}
+
+/// Drops power-ups that have run out and adds up the modifiers of the ones still going.
+fn player_powerups(world: &mut World) {
+    let time = world.time;
+    world
+        .player
+        .power_ups
+        .retain(|active| time < active.expires_at);
+
+    let mut modifiers = Modifiers::default();
+    for active in world.player.power_ups.iter() {
+        modifiers.combine(&world.content.power_ups[active.id.0].modifiers);
+    }
+    world.player.modifiers = modifiers;
+}
+
+/// Starts power-up `id`, or follows its stacking rule if it's already running.
+fn grant_power_up(world: &mut World, id: PowerUpId) {
+    let def = &world.content.power_ups[id.0];
+    let fresh = ActivePowerUp {
+        id,
+        started_at: world.time,
+        expires_at: world.time + def.duration,
+    };
+    let power_ups = &mut world.player.power_ups;
+    let running = power_ups.iter().filter(|active| active.id == id).count();
+    // The copy closest to running out, which is the one a refresh or an extension goes to
+    let oldest = power_ups
+        .iter()
+        .enumerate()
+        .filter(|(_, active)| active.id == id)
+        .min_by(|(_, a), (_, b)| a.expires_at.total_cmp(&b.expires_at))
+        .map(|(index, _)| index);
+
+    match (def.stacking, oldest) {
+        (_, None) => power_ups.push(fresh),
+        (Stacking::Stack, Some(_)) if running < def.max_stacks as usize => power_ups.push(fresh),
+        (Stacking::Refresh | Stacking::Stack, Some(index)) => power_ups[index] = fresh,
+        (Stacking::Extend, Some(index)) => power_ups[index].expires_at += def.duration,
+    }
+}
+
+fn bullet_update(world: &mut World, input: &Input, dt: f32, events: &mut Vec<Event>) {
+    player_reload(world, input, events);
+
+    let current_time = world.time;
+    let player_pos = Vec2::new(world.player.position.x, world.player.position.y + 16.);
+    let weapon = &world.content.weapons[world.player.weapon.0];
+
+    let trigger = match weapon.fire_mode {
+        FireMode::Semi => input.fire_pressed,
+        FireMode::Auto => input.fire_down,
+    };
+    let modifiers = world.player.modifiers;
+    let fire_rate = weapon.fire_rate * modifiers.fire_rate;
+    let ammo = &mut world.player.ammo[world.player.weapon.0];
+    let loaded = weapon.magazine.is_none() || ammo.loaded > 0;
+
+    // Only a fresh pull of the trigger clicks, rather than every tick it's held
+    if input.fire_pressed && !loaded && world.player.reloading_until.is_none() {
+        events.push(Event::DryFire {
+            weapon: world.player.weapon,
+        });
+    }
+
+    // Aiming at the player's own position gives no direction to shoot in
+    let aim_direction = (input.aim - player_pos).normalize_or_zero();
+
+    if trigger
+        && loaded
+        && aim_direction != Vec2::ZERO
+        && world.player.reloading_until.is_none()
+        && current_time - world.player.last_shot > fire_rate
+    {
+        events.push(Event::Shot {
+            weapon: world.player.weapon,
+        });
+        world.shots[world.player.weapon.0] += 1;
+        if weapon.magazine.is_some() {
+            ammo.loaded -= 1;
+        }
+        let damage = (weapon.damage as f32 * modifiers.damage).round() as i32;
+        let pellets = weapon.pellets + modifiers.spread;
+        let spread = weapon.spread + modifiers.spread as f32 * SPREAD_SHOT_ANGLE;
+
+        let mut targets = Vec::new();
+        if pellets <= 1 {
+            targets.push(input.aim);
+        } else {
+            for bullet_direction in fan(aim_direction, pellets, spread) {
+                targets.push(player_pos + bullet_direction * 100.0);
+            }
+        }
+        for target in targets {
+            let mut bullet = Bullet::new(
+                player_pos,
+                target,
+                true,
+                weapon.projectile_speed,
+                damage,
+                weapon.range,
+                Faction::Player,
+            );
+            bullet.projectile = modifiers.projectile(&weapon.projectile);
+            bullet.lifetime = bullet.projectile.lifetime.unwrap_or(f32::INFINITY);
+            world.bullets.push(bullet);
+        }
+
+        world.player.last_shot = current_time
+    }
+
+    // The grid is from the end of last tick, and enemies have only been added to since
+    let level = &world.content.level;
+    for bullet in world.bullets.iter_mut() {
+        if bullet.projectile.homing > 0.0 {
+            let centre = bullet.coll_rect.center();
+            if let Some(target) = nearest_enemy(&world.enemies, &world.enemy_grid, centre) {
+                bullet.velocity = turn_towards(
+                    bullet.velocity,
+                    target - centre,
+                    bullet.projectile.homing.to_radians() * dt,
+                );
+            }
+        }
+
+        let previous = bullet.position;
+        bullet.position += bullet.velocity * bullet.speed * dt;
+        bullet.range -= bullet.speed * dt;
+        bullet.lifetime -= dt;
+
+        // Walls, and the edges of the level, which count as walls
+        if level.is_solid_at(bullet.position) {
+            if bullet.projectile.bounces > 0 {
+                bullet.projectile.bounces -= 1;
+                // Turn back whichever way took it into the wall, or both ways off a corner
+                let blocked_x = level.is_solid_at(vec2(bullet.position.x, previous.y));
+                let blocked_y = level.is_solid_at(vec2(previous.x, bullet.position.y));
+                if blocked_x || !blocked_y {
+                    bullet.velocity.x = -bullet.velocity.x;
+                }
+                if blocked_y || !blocked_x {
+                    bullet.velocity.y = -bullet.velocity.y;
+                }
+                bullet.position = previous;
+            } else {
+                bullet.is_active = false;
+                if bullet.projectile.blast_radius > 0.0 {
+                    let blast = Blast {
+                        position: previous + bullet.coll_rect.size() / 2.0,
+                        radius: bullet.projectile.blast_radius,
+                        at: world.time,
+                    };
+                    detonate(
+                        blast,
+                        bullet.blast_damage(),
+                        &mut world.enemies,
+                        &world.enemy_grid,
+                        &mut world.blasts,
+                        events,
+                    );
+                }
+            }
+        } else if bullet.range <= 0.0 || bullet.lifetime <= 0.0 {
+            bullet.is_active = false;
+            // Rockets go off at the end of their flight rather than fizzling out
+            if bullet.projectile.blast_radius > 0.0 {
+                let blast = Blast {
+                    position: bullet.position + bullet.coll_rect.size() / 2.0,
+                    radius: bullet.projectile.blast_radius,
+                    at: world.time,
+                };
+                detonate(
+                    blast,
+                    bullet.blast_damage(),
+                    &mut world.enemies,
+                    &world.enemy_grid,
+                    &mut world.blasts,
+                    events,
+                );
+            }
+        }
+
+        bullet.coll_rect.x = bullet.position.x;
+        bullet.coll_rect.y = bullet.position.y;
+    }
+
+    world.bullets.retain(|bullet| bullet.is_active);
+}
+
+/// Middle of the living enemy closest to `point`, if any are within `HOMING_RANGE`.
+fn nearest_enemy(enemies: &[Enemy], grid: &SpatialGrid, point: Vec2) -> Option<Vec2> {
+    let area = Rect::new(
+        point.x - HOMING_RANGE,
+        point.y - HOMING_RANGE,
+        HOMING_RANGE * 2.0,
+        HOMING_RANGE * 2.0,
+    );
+    grid.query(&area)
+        .map(|index| &enemies[index])
+        .filter(|enemy| enemy.health > 0)
+        .map(|enemy| enemy.coll_rect.center())
+        .filter(|centre| centre.distance_squared(point) <= HOMING_RANGE * HOMING_RANGE)
+        .min_by(|a, b| {
+            a.distance_squared(point)
+                .total_cmp(&b.distance_squared(point))
+        })
+}
+
+/// `direction` turned towards `wanted`, by no more than `max_angle` radians.
+fn turn_towards(direction: Vec2, wanted: Vec2, max_angle: f32) -> Vec2 {
+    let current = atan2f(direction.y, direction.x);
+    let turn = (atan2f(wanted.y, wanted.x) - current + PI).rem_euclid(TAU) - PI;
+    let angle = current + turn.clamp(-max_angle, max_angle);
+    Vec2::new(cosf(angle), sinf(angle))
+}
+
+/// Sets off `blast`, damaging every enemy it reaches.
+fn detonate(
+    blast: Blast,
+    damage: i32,
+    enemies: &mut [Enemy],
+    grid: &SpatialGrid,
+    blasts: &mut Vec<Blast>,
+    events: &mut Vec<Event>,
+) {
+    let area = Rect::new(
+        blast.position.x - blast.radius,
+        blast.position.y - blast.radius,
+        blast.radius * 2.0,
+        blast.radius * 2.0,
+    );
+    for index in grid.query(&area) {
+        let enemy = &mut enemies[index];
+        // The nearest point of the enemy to the middle of the blast
+        let nearest = blast.position.clamp(
+            enemy.coll_rect.point(),
+            enemy.coll_rect.point() + enemy.coll_rect.size(),
+        );
+        if nearest.distance(blast.position) <= blast.radius {
+            damage_enemy(enemy, damage);
+        }
+    }
+    blasts.push(blast);
+    events.push(Event::Exploded);
+}
+
+/// `count` directions spread evenly over `spread` degrees, centred on `direction`.
+fn fan(direction: Vec2, count: u32, spread: f32) -> Vec<Vec2> {
+    if count <= 1 {
+        return vec![direction];
+    }
+
+    let spread_angle = spread.to_radians();
+    let base_angle = atan2f(direction.y, direction.x);
+    let spread_increment = spread_angle / (count - 1) as f32;
+
+    (0..count)
+        .map(|i| {
+            let angle = base_angle + (-spread_angle / 2.0 + spread_increment * i as f32);
+            Vec2::new(cosf(angle), sinf(angle))
+        })
+        .collect()
+}
+
+fn enemy_update(world: &mut World, dt: f32, events: &mut Vec<Event>) {
+    let player_pos: Vec2 = world.player.position;
+    rebuild_enemy_grid(world);
+    world
+        .flow
+        .update(&world.content.level, world.player.coll_rect.center());
+
+    // Work out every enemy's heading first, so they all steer off where the others were at the
+    // start of the tick rather than wherever the ones before them in the list have moved to
+    let mut headings = Vec::with_capacity(world.enemies.len());
+    for (index, enemy) in world.enemies.iter().enumerate() {
+        // Follow the flow field around walls, or make straight for the player once in their tile
+        let mut normalized_direction = world
+            .flow
+            .heading(enemy.coll_rect.center())
+            .unwrap_or_else(|| (player_pos - enemy.position).normalize_or_zero());
+
+        if tactics(&world.content, enemy).0 == Behaviour::Orbit {
+            // Mostly sideways around the player, drifting in a little at a time
+            normalized_direction = normalized_direction.perp() + normalized_direction * 0.5;
+        }
+
+        // Check for collisions with other enemies and adjust position
+        let mut neighbours = 0;
+        for other_index in world.enemy_grid.query(&enemy.coll_rect) {
+            let other_enemy = &world.enemies[other_index];
+            if other_index != index && enemy.coll_rect.overlaps(&other_enemy.coll_rect) {
+                let avoidance_direction = enemy.position - other_enemy.position;
+                let avoidance_distance = avoidance_direction.length();
+
+                if avoidance_distance != 0.0 {
+                    // Adjust the normalized direction based on avoidance direction
+                    normalized_direction += avoidance_direction.normalize() / avoidance_distance;
+                }
+
+                neighbours += 1;
+                if neighbours == MAX_NEIGHBOURS {
+                    break;
+                }
+            }
+        }
+        headings.push(normalized_direction);
+    }
+
+    for (enemy, heading) in world.enemies.iter_mut().zip(headings) {
+        if enemy.health <= 0 {
+            world.score += world.content.enemies[enemy.kind.0].score;
+            events.push(Event::EnemyKilled);
+            if let Some(boss) = enemy.boss {
+                if let Some(encounter) = world
+                    .encounter
+                    .as_mut()
+                    .filter(|encounter| encounter.boss == boss)
+                {
+                    encounter.defeated_at = Some(world.time);
+                }
+                events.push(Event::BossDefeated { boss });
+            }
+
+            let drops = &world.content.enemies[enemy.kind.0].drops;
+            if let Some(drop) = drops
+                .iter()
+                .find(|drop| world.rng.gen::<f32>() < drop.chance)
+            {
+                let kind = drop.kind.unwrap();
+                world.pickups.push(Pickup {
+                    kind,
+                    position: enemy.coll_rect.center(),
+                    expires_at: Some(world.time + world.content.pickups[kind.0].lifetime),
+                    spot: None,
+                });
+            }
+        }
+        enemy.position = world.content.level.slide(
+            enemy.position,
+            enemy.coll_rect.size(),
+            heading * enemy.speed * dt,
+        );
+        enemy.coll_rect.x = enemy.position.x;
+        enemy.coll_rect.y = enemy.position.y;
+    }
+
+    world.enemies.retain(|enemy| enemy.health > 0);
+}
+
+/// Lets every enemy with a ranged attack fire at the player once they're in range and reloaded.
+fn enemy_attacks(world: &mut World) {
+    let target = world.player.coll_rect.center();
+
+    for enemy in world.enemies.iter_mut() {
+        let Some(ranged) = tactics(&world.content, enemy).1 else {
+            continue;
+        };
+        let origin = enemy.coll_rect.center();
+        let to_player = target - origin;
+        if to_player.length() > ranged.range || world.time - enemy.last_shot < ranged.fire_rate {
+            continue;
+        }
+        // Right on top of the player there's no way to aim, hold fire until there is
+        let Some(direction) = to_player.try_normalize() else {
+            continue;
+        };
+        enemy.last_shot = world.time;
+
+        let directions = match ranged.pattern {
+            FirePattern::Aimed => vec![direction],
+            FirePattern::Spread => fan(direction, ranged.count, ranged.spread),
+            // Leave a gap the size of the others where the first and last would meet
+            FirePattern::Radial => fan(
+                direction,
+                ranged.count,
+                360.0 - 360.0 / ranged.count.max(1) as f32,
+            ),
+        };
+        let damage = (ranged.damage as f32 * world.difficulty.enemy_damage()).round() as i32;
+        for direction in directions {
+            world.bullets.push(Bullet::new(
+                origin,
+                origin + direction,
+                true,
+                ranged.projectile_speed,
+                damage,
+                ranged.range,
+                Faction::Enemy,
+            ));
+        }
+    }
+}
+
+/// Puts pickups back on their map spots once due, clears away dropped ones that have timed out
+/// and hands the player whatever is in reach.
+fn pickup_update(world: &mut World, events: &mut Vec<Event>) {
+    for (index, spot) in world.pickup_spots.iter_mut().enumerate() {
+        if world.time >= spot.next_spawn {
+            spot.next_spawn = f64::INFINITY;
+            world.pickups.push(Pickup {
+                kind: spot.kind,
+                position: spot.position,
+                expires_at: None,
+                spot: Some(index),
+            });
+        }
+    }
+
+    let time = world.time;
+    world
+        .pickups
+        .retain(|pickup| pickup.expires_at.is_none_or(|at| time < at));
+
+    let player_pos = world.player.coll_rect.center();
+    let mut index = 0;
+    while index < world.pickups.len() {
+        let pickup = &world.pickups[index];
+        if pickup.position.distance(player_pos) > world.player.pickup_radius {
+            index += 1;
+            continue;
+        }
+
+        let pickup = world.pickups.swap_remove(index);
+        let def = &world.content.pickups[pickup.kind.0];
+        if let Some(spot) = pickup.spot {
+            world.pickup_spots[spot].next_spawn = world.time + def.respawn;
+        }
+        match def.effect {
+            Effect::Health { amount } => {
+                world.player.health = (world.player.health + amount).min(world.player.max_health);
+            }
+            Effect::Weapon { id, ammo, .. } => {
+                let weapon = id.unwrap();
+                unlock_weapon(&mut world.player, weapon, world.time, events);
+                switch_weapon(&mut world.player, weapon);
+                let carried = &mut world.player.ammo[weapon.0];
+                carried.reserve = carried.reserve.map(|reserve| reserve + ammo);
+            }
+            Effect::PowerUp { id, .. } => grant_power_up(world, id.unwrap()),
+        }
+        events.push(Event::PickedUp {
+            pickup: pickup.kind,
+        });
+    }
+}
+
+fn rebuild_enemy_grid(world: &mut World) {
+    world.enemy_grid.clear();
+    for (index, enemy) in world.enemies.iter().enumerate() {
+        world.enemy_grid.insert(index, &enemy.coll_rect);
+    }
+}
+
+fn collision_check(world: &mut World, events: &mut Vec<Event>) {
+    // Enemies have moved since the grid was last built
+    rebuild_enemy_grid(world);
+
+    for bullet in world.bullets.iter_mut() {
+        match bullet.owner {
+            Faction::Player => {
+                for index in world.enemy_grid.query(&bullet.coll_rect) {
+                    let enemy = &mut world.enemies[index];
+                    if bullet.is_active
+                        && !bullet.hit.contains(&enemy.id)
+                        && enemy.coll_rect.overlaps(&bullet.coll_rect)
+                    {
+                        damage_enemy(enemy, bullet.damage);
+                        events.push(Event::EnemyHit);
+                        if bullet.projectile.piercing > 0 {
+                            bullet.projectile.piercing -= 1;
+                            bullet.hit.push(enemy.id);
+                        } else {
+                            bullet.is_active = false;
+                        }
+                    }
+                }
+
+                // Went off on whatever finally stopped it
+                if !bullet.is_active && bullet.projectile.blast_radius > 0.0 {
+                    let blast = Blast {
+                        position: bullet.coll_rect.center(),
+                        radius: bullet.projectile.blast_radius,
+                        at: world.time,
+                    };
+                    detonate(
+                        blast,
+                        bullet.blast_damage(),
+                        &mut world.enemies,
+                        &world.enemy_grid,
+                        &mut world.blasts,
+                        events,
+                    );
+                }
+            }
+            Faction::Enemy => {
+                if bullet.is_active && bullet.coll_rect.overlaps(&world.player.coll_rect) {
+                    bullet.is_active = false;
+                    damage_player(&mut world.player, bullet.damage as f32);
+                    events.push(Event::PlayerHit);
+                }
+            }
+        }
+    }
+
+    for index in world.enemy_grid.query(&world.player.coll_rect) {
+        let enemy = &mut world.enemies[index];
+        if enemy.coll_rect.overlaps(&world.player.coll_rect) && enemy.can_attack {
+            let damage = world.content.enemies[enemy.kind.0].contact_damage as f32
+                * world.difficulty.enemy_damage();
+            damage_player(&mut world.player, damage);
+            enemy.can_attack = false;
+            enemy.dmg_cd = world.time;
+            events.push(Event::PlayerHit);
+        }
+    }
+
+    for enemy in world.enemies.iter_mut() {
+        // reset can attack to true after a few seconds
+        let cooldown = world.content.enemies[enemy.kind.0].attack_cooldown;
+        if !enemy.can_attack && (world.time - enemy.dmg_cd) > cooldown {
+            enemy.can_attack = true
+        }
+    }
+}
+
+fn damage_enemy(enemy: &mut Enemy, dmg: i32) {
+    enemy.health -= dmg;
+}
+
+/// Takes `damage` off the player's health, less whatever their shield blocks.
+fn damage_player(player: &mut Player, damage: f32) {
+    player.health -= (damage * (1.0 - player.modifiers.shield)).round() as i32;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn world(seed: u64) -> World {
+        World::new(
+            seed,
+            Difficulty::Normal,
+            Rc::new(Content::load(&mut assets::Assets::new())),
+        )
+    }
+
+    /// A world that won't start a wave, so only what a test puts in it is there.
+    fn quiet_world() -> World {
+        let mut world = world(0);
+        world.waves.phase = WavePhase::Intermission {
+            until: f64::INFINITY,
+        };
+        world
+    }
+
+    /// One of the first kind of enemy, centred on `centre`.
+    fn add_enemy(world: &mut World, centre: Vec2) {
+        let def = &world.content.enemies[0];
+        let mut enemy = Enemy::new(centre - Vec2::from(def.size) / 2.0, EnemyKind(0), def);
+        enemy.id = world.next_enemy_id;
+        world.next_enemy_id += 1;
+        world.enemies.push(enemy);
+    }
+
+    #[test]
+    fn player_accelerates_and_moves_each_tick() {
+        let mut world = quiet_world();
+        let start = world.player.position;
+        let input = Input {
+            right: true,
+            ..Default::default()
+        };
+
+        // Speed picked up each tick, less what friction takes off
+        let speed = (world.player.acceleration - world.player.friction) * TIME_STEP;
+
+        world.tick(&input, TIME_STEP);
+        assert!(world.player.velocity.abs_diff_eq(vec2(speed, 0.0), 1e-3));
+        assert!(world
+            .player
+            .position
+            .abs_diff_eq(start + vec2(speed * TIME_STEP, 0.0), 1e-3));
+
+        world.tick(&input, TIME_STEP);
+        assert!(world
+            .player
+            .velocity
+            .abs_diff_eq(vec2(speed * 2.0, 0.0), 1e-3));
+        assert!(world
+            .player
+            .position
+            .abs_diff_eq(start + vec2(speed * 3.0 * TIME_STEP, 0.0), 1e-3));
+    }
+
+    #[test]
+    fn bullet_kills_enemy() {
+        let mut world = quiet_world();
+        let centre = world.player.coll_rect.center() + vec2(80.0, 0.0);
+        add_enemy(&mut world, centre);
+        let health = world.enemies[0].health;
+        world.bullets.push(Bullet::new(
+            centre,
+            centre + Vec2::X,
+            true,
+            0.0,
+            health,
+            f32::MAX,
+            Faction::Player,
+        ));
+
+        let mut events = world.tick(&Input::default(), TIME_STEP);
+        assert!(events.contains(&Event::EnemyHit));
+        assert!(!events.contains(&Event::EnemyKilled));
+
+        // The dead are counted and cleared away on the next tick, along with spent bullets
+        events.extend(world.tick(&Input::default(), TIME_STEP));
+        assert!(events.contains(&Event::EnemyKilled));
+        assert!(world.enemies.is_empty());
+        assert!(world.bullets.is_empty());
+        assert_eq!(world.score, world.content.enemies[0].score);
+    }
+
+    #[test]
+    fn touching_an_enemy_hurts_the_player() {
+        let mut world = quiet_world();
+        let health = world.player.health;
+        let centre = world.player.coll_rect.center();
+        add_enemy(&mut world, centre);
+
+        let events = world.tick(&Input::default(), TIME_STEP);
+        let damage =
+            world.content.enemies[0].contact_damage as f32 * world.difficulty.enemy_damage();
+        assert!(events.contains(&Event::PlayerHit));
+        assert_eq!(world.player.health, health - damage.round() as i32);
+
+        // Then not again until the enemy's cooldown is up
+        let events = world.tick(&Input::default(), TIME_STEP);
+        assert!(!events.contains(&Event::PlayerHit));
+    }
+
+    /// Moves about, sweeps the aim round and fires in bursts.
+    fn scripted_input(tick: usize) -> Input {
+        let angle = tick as f32 * 0.01;
+        Input {
+            up: tick % 240 < 60,
+            down: tick % 240 >= 180,
+            left: tick % 400 < 100,
+            right: tick % 400 >= 300,
+            fire_pressed: tick.is_multiple_of(30),
+            fire_down: tick % 60 < 40,
+            aim: vec2(640.0 + 300.0 * cosf(angle), 360.0 + 300.0 * sinf(angle)),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn same_seed_and_inputs_give_the_same_run() {
+        let mut a = world(7);
+        let mut b = world(7);
+        for tick in 0..3000 {
+            let input = scripted_input(tick);
+            a.tick(&input, TIME_STEP);
+            b.tick(&input, TIME_STEP);
+        }
+        // Enough happened for a drift to have something to show up in
+        assert!(a.waves.number > 1);
+        assert!(a.score > 0);
+        assert_eq!(a.checksum(), b.checksum());
+    }
+}